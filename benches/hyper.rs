@@ -17,15 +17,39 @@ use hyper::service::service_fn;
 use hyper::{Error, Request, Response, Uri};
 use norn_executor::spawn;
 use norn_timer::Clock;
-use norn_uring::net::{TcpListener, TcpSocket};
+use norn_uring::bufring::BufRing;
+use norn_uring::net::{TcpListener, TcpSocket, TcpStream};
+
+/// How sockets are converted into streams for hyper.
+#[derive(Debug, Clone, Copy)]
+enum StreamMode {
+    /// [`TcpSocket::into_stream`], waiting for readiness then performing the syscall.
+    Ready,
+    /// [`TcpSocket::into_completion_stream`], submitting the I/O to io_uring.
+    Completion,
+}
+
+impl StreamMode {
+    fn into_stream(self, socket: TcpSocket, ring: &BufRing) -> TcpStream {
+        match self {
+            StreamMode::Ready => socket.into_stream(),
+            StreamMode::Completion => socket.into_completion_stream(ring),
+        }
+    }
+}
 
 struct HyperBench {
     clients: usize,
     requests: usize,
+    mode: StreamMode,
 }
 impl HyperBench {
-    fn new(clients: usize, requests: usize) -> Self {
-        Self { clients, requests }
+    fn new(clients: usize, requests: usize, mode: StreamMode) -> Self {
+        Self {
+            clients,
+            requests,
+            mode,
+        }
     }
 }
 impl bencher::TDynBenchFn for HyperBench {
@@ -40,6 +64,9 @@ impl bencher::TDynBenchFn for HyperBench {
         let driver = norn_uring::Driver::new(builder, 32).unwrap();
         let driver = norn_timer::Driver::new(driver, Clock::system());
         let mut ex = norn_executor::LocalExecutor::new(driver);
+        let ring = ex
+            .block_on(async { BufRing::builder(0).buf_cnt(256).buf_len(16 * 1024).build() })
+            .unwrap();
 
         b.iter(|| {
             ex.block_on(async {
@@ -47,13 +74,18 @@ impl bencher::TDynBenchFn for HyperBench {
                     .await
                     .unwrap();
                 let server_addr = listener.local_addr().unwrap();
-                spawn(http2_server(listener)).detach();
+                spawn(http2_server(listener, self.mode, ring.clone())).detach();
                 let mut handles = vec![];
                 let uri: Uri = format!("http://127.0.0.1:{}", server_addr.port())
                     .parse()
                     .unwrap();
                 for _ in 0..self.clients {
-                    let handle = spawn(http2_client(uri.clone(), self.requests));
+                    let handle = spawn(http2_client(
+                        uri.clone(),
+                        self.requests,
+                        self.mode,
+                        ring.clone(),
+                    ));
                     handles.push(handle);
                 }
                 for handle in handles {
@@ -66,19 +98,25 @@ impl bencher::TDynBenchFn for HyperBench {
 
 pub fn benches() -> ::std::vec::Vec<TestDescAndFn> {
     let mut benches = vec![];
-    for num_clients in [1] {
-        for n in [128, 1024] {
-            let per_client = cmp::max(n / num_clients, 1);
-            benches.push(TestDescAndFn {
-                desc: TestDesc {
-                    name: Cow::from(format!(
-                        "bench_hyper/num_clients={}/num_requests={}",
-                        num_clients, n
-                    )),
-                    ignore: false,
-                },
-                testfn: TestFn::DynBenchFn(Box::new(HyperBench::new(num_clients, per_client))),
-            })
+    for mode in [StreamMode::Ready, StreamMode::Completion] {
+        for num_clients in [1] {
+            for n in [128, 1024] {
+                let per_client = cmp::max(n / num_clients, 1);
+                benches.push(TestDescAndFn {
+                    desc: TestDesc {
+                        name: Cow::from(format!(
+                            "bench_hyper/mode={:?}/num_clients={}/num_requests={}",
+                            mode, num_clients, n
+                        )),
+                        ignore: false,
+                    },
+                    testfn: TestFn::DynBenchFn(Box::new(HyperBench::new(
+                        num_clients,
+                        per_client,
+                        mode,
+                    ))),
+                })
+            }
         }
     }
     benches
@@ -94,12 +132,17 @@ fn main() {
     run_tests_console(&test_opts, all).unwrap();
 }
 
-async fn http2_client(url: hyper::Uri, n_req: usize) -> Result<(), Box<dyn std::error::Error>> {
+async fn http2_client(
+    url: hyper::Uri,
+    n_req: usize,
+    mode: StreamMode,
+    ring: BufRing,
+) -> Result<(), Box<dyn std::error::Error>> {
     let host = url.host().expect("no host");
     let port = url.port_u16().unwrap_or(80);
     let addr = format!("{}:{}", host, port).parse::<SocketAddr>()?;
     let socket = TcpSocket::connect(addr).await?;
-    let stream = socket.into_stream();
+    let stream = mode.into_stream(socket, &ring);
     let stream = Box::pin(NornIo { inner: stream });
     let (mut sender, conn) = http2::handshake(NornEx, stream).await?;
     spawn(async move {
@@ -142,12 +185,16 @@ impl hyper::body::Body for Body {
     }
 }
 
-async fn http2_server(listener: TcpListener) -> Result<(), Box<dyn std::error::Error>> {
+async fn http2_server(
+    listener: TcpListener,
+    mode: StreamMode,
+    ring: BufRing,
+) -> Result<(), Box<dyn std::error::Error>> {
     let mut incoming = pin!(listener.incoming());
     while let Some(next) = incoming.next().await {
         let socket = next?;
         let io = NornIo {
-            inner: socket.into_stream(),
+            inner: mode.into_stream(socket, &ring),
         };
         let service = service_fn(move |_| async move {
             let body = Body {
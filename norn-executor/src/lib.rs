@@ -69,14 +69,22 @@ impl<P: park::Park> LocalExecutor<P> {
             if let Some(result) = root.try_poll() {
                 return result;
             }
-            while let Some(next) = self.taskqueue.next() {
+            // Only run the tasks which were runnable at the start of this tick, so
+            // a task which keeps waking itself can't prevent the driver from making
+            // progress on I/O it may be waiting on.
+            let mut budget = self.taskqueue.runnable();
+            while budget > 0 {
+                let Some(next) = self.taskqueue.next() else {
+                    break;
+                };
                 next.run();
+                budget -= 1;
                 if self.park.needs_park() {
                     break;
                 }
             }
             let mut mode = park::ParkMode::NextCompletion;
-            if root.is_notified() {
+            if root.is_notified() || self.taskqueue.runnable() > 0 {
                 mode = park::ParkMode::NoPark;
             }
             self.park.park(mode).unwrap();
@@ -137,12 +145,61 @@ impl<P: park::Park> Drop for LocalExecutor<P> {
 
 #[cfg(test)]
 mod tests {
-    use std::task::Poll;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::task::{Poll, Waker};
 
-    use crate::park::SpinPark;
+    use crate::park::{Park, ParkMode, SpinPark};
 
     use super::*;
 
+    /// A [`Park`] whose I/O completes the first time it is parked.
+    #[derive(Default)]
+    struct IoPark {
+        parked: Rc<Cell<bool>>,
+        waker: Rc<RefCell<Option<Waker>>>,
+    }
+
+    impl Park for IoPark {
+        type Unparker = <SpinPark as Park>::Unparker;
+
+        type Guard = ();
+
+        fn park(&mut self, _: ParkMode) -> Result<(), std::io::Error> {
+            self.parked.set(true);
+            if let Some(waker) = self.waker.borrow_mut().take() {
+                waker.wake();
+            }
+            Ok(())
+        }
+
+        fn enter(&self) -> Self::Guard {}
+
+        fn unparker(&self) -> Self::Unparker {
+            SpinPark.unparker()
+        }
+
+        fn needs_park(&self) -> bool {
+            false
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    /// Yields to the executor once, waking itself.
+    async fn yield_now() {
+        let mut yielded = false;
+        std::future::poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+
     #[test]
     fn block_on() {
         let mut executor = LocalExecutor::new(SpinPark);
@@ -205,4 +262,41 @@ mod tests {
             assert_eq!(res, 2);
         })
     }
+
+    #[test]
+    fn self_waking_task_does_not_starve_park() {
+        const MAX_SPINS: usize = 1000;
+        let park = IoPark::default();
+        let (parked, waker) = (park.parked.clone(), park.waker.clone());
+        let mut executor = LocalExecutor::new(park);
+
+        let spins = executor.block_on(async move {
+            let done = Rc::new(Cell::new(false));
+            let spins = Rc::new(Cell::new(0));
+            // The spinner gives up eventually, so a starved park fails the
+            // assertion below rather than hanging the test.
+            crate::spawn({
+                let (done, spins) = (done.clone(), spins.clone());
+                async move {
+                    while !done.get() && spins.get() < MAX_SPINS {
+                        spins.set(spins.get() + 1);
+                        yield_now().await;
+                    }
+                }
+            })
+            .detach();
+
+            std::future::poll_fn(|cx| {
+                if parked.get() {
+                    return Poll::Ready(());
+                }
+                *waker.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            })
+            .await;
+            done.set(true);
+            spins.get()
+        });
+        assert!(spins < MAX_SPINS, "park was starved for {spins} spins");
+    }
 }
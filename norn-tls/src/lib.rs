@@ -0,0 +1,219 @@
+//! TLS for [norn-uring] TCP streams using [rustls].
+//!
+//! [`TlsConnector`] and [`TlsAcceptor`] perform the client and server
+//! handshakes over a [`TcpStream`], returning a [`TlsStream`]. Both readiness
+//! and completion based streams are supported, see
+//! [`TcpSocket::into_stream`] and [`TcpSocket::into_completion_stream`].
+//!
+//! ALPN, the supported protocol versions, certificates and session resumption
+//! are configured using the [`ClientConfig`] and [`ServerConfig`] passed to
+//! the connector and acceptor. The server name sent by the client (SNI) is the
+//! one passed to [`TlsConnector::connect`].
+//!
+//! [norn-uring]: norn_uring
+//! [`TcpSocket::into_stream`]: norn_uring::net::TcpSocket::into_stream
+//! [`TcpSocket::into_completion_stream`]: norn_uring::net::TcpSocket::into_completion_stream
+#![deny(
+    missing_docs,
+    missing_debug_implementations,
+    rust_2018_idioms,
+    clippy::missing_safety_doc
+)]
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use norn_uring::net::TcpStream;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, CommonState, HandshakeKind, ServerConfig};
+
+pub use rustls;
+
+const LOG: &str = "norn_tls";
+
+/// Performs the client side of TLS handshakes.
+#[derive(Clone)]
+pub struct TlsConnector {
+    inner: tokio_rustls::TlsConnector,
+}
+
+impl TlsConnector {
+    /// Creates a connector using the given configuration.
+    pub fn new(config: Arc<ClientConfig>) -> Self {
+        let inner = tokio_rustls::TlsConnector::from(config);
+        Self { inner }
+    }
+
+    /// Performs the client handshake over `stream`.
+    ///
+    /// `server_name` is sent to the server (SNI) unless disabled in the
+    /// configuration, and is the name the server's certificate is verified for.
+    pub async fn connect(
+        &self,
+        server_name: ServerName<'static>,
+        stream: TcpStream,
+    ) -> io::Result<TlsStream> {
+        let stream = self.inner.connect(server_name, stream).await?;
+        let stream = TlsStream {
+            inner: stream.into(),
+        };
+        log::debug!(target: LOG, "connect.handshake {:?}", stream.handshake_kind());
+        Ok(stream)
+    }
+}
+
+impl From<Arc<ClientConfig>> for TlsConnector {
+    fn from(config: Arc<ClientConfig>) -> Self {
+        Self::new(config)
+    }
+}
+
+impl std::fmt::Debug for TlsConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConnector").finish()
+    }
+}
+
+/// Performs the server side of TLS handshakes.
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    inner: tokio_rustls::TlsAcceptor,
+}
+
+impl TlsAcceptor {
+    /// Creates an acceptor using the given configuration.
+    pub fn new(config: Arc<ServerConfig>) -> Self {
+        let inner = tokio_rustls::TlsAcceptor::from(config);
+        Self { inner }
+    }
+
+    /// Performs the server handshake over `stream`.
+    pub async fn accept(&self, stream: TcpStream) -> io::Result<TlsStream> {
+        let stream = self.inner.accept(stream).await?;
+        let stream = TlsStream {
+            inner: stream.into(),
+        };
+        log::debug!(target: LOG, "accept.handshake {:?}", stream.handshake_kind());
+        Ok(stream)
+    }
+}
+
+impl From<Arc<ServerConfig>> for TlsAcceptor {
+    fn from(config: Arc<ServerConfig>) -> Self {
+        Self::new(config)
+    }
+}
+
+impl std::fmt::Debug for TlsAcceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsAcceptor").finish()
+    }
+}
+
+/// A TCP stream protected by TLS.
+///
+/// Data is encrypted and decrypted in memory, so this works with either kind
+/// of [`TcpStream`].
+pub struct TlsStream {
+    inner: tokio_rustls::TlsStream<TcpStream>,
+}
+
+impl TlsStream {
+    /// Returns the underlying TCP stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        self.common().0
+    }
+
+    /// Returns the local address of the underlying TCP stream.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().local_addr()
+    }
+
+    /// Returns the remote address of the underlying TCP stream.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+
+    /// Returns the protocol agreed using ALPN, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.common().1.alpn_protocol()
+    }
+
+    /// Returns the server name sent by the client (SNI).
+    ///
+    /// This is only known on the server side of the connection.
+    pub fn server_name(&self) -> Option<&str> {
+        match &self.inner {
+            tokio_rustls::TlsStream::Client(_) => None,
+            tokio_rustls::TlsStream::Server(stream) => stream.get_ref().1.server_name(),
+        }
+    }
+
+    /// Returns true if the handshake resumed a previous session.
+    pub fn is_resumed(&self) -> bool {
+        self.handshake_kind() == Some(HandshakeKind::Resumed)
+    }
+
+    fn handshake_kind(&self) -> Option<HandshakeKind> {
+        self.common().1.handshake_kind()
+    }
+
+    fn common(&self) -> (&TcpStream, &CommonState) {
+        self.inner.get_ref()
+    }
+}
+
+impl std::fmt::Debug for TlsStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (stream, state) = self.common();
+        f.debug_struct("TlsStream")
+            .field("stream", stream)
+            .field("protocol_version", &state.protocol_version())
+            .field("alpn_protocol", &state.alpn_protocol())
+            .finish()
+    }
+}
+
+impl tokio::io::AsyncRead for TlsStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncWrite for TlsStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    /// Sends a close notification to the peer and shuts down the write half of
+    /// the TCP stream.
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,183 @@
+use std::io;
+use std::sync::Arc;
+
+use norn_executor::spawn;
+use norn_tls::rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName};
+use norn_tls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use norn_tls::{TlsAcceptor, TlsConnector};
+use norn_uring::bufring::BufRing;
+use norn_uring::net::{TcpListener, TcpSocket};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+mod util;
+
+/// A self-signed certificate for `localhost`, generated for each test.
+struct TestCert {
+    cert: CertificateDer<'static>,
+    key: PrivatePkcs8KeyDer<'static>,
+}
+
+impl TestCert {
+    fn generate() -> Self {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        Self {
+            cert: certified.cert.der().clone(),
+            key: PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()),
+        }
+    }
+
+    fn server_config(&self, alpn: &[&[u8]]) -> Arc<ServerConfig> {
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![self.cert.clone()], self.key.clone_key().into())
+            .unwrap();
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        Arc::new(config)
+    }
+
+    fn client_config(&self, alpn: &[&[u8]]) -> Arc<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.cert.clone()).unwrap();
+        let mut config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        Arc::new(config)
+    }
+}
+
+fn localhost() -> ServerName<'static> {
+    ServerName::try_from("localhost").unwrap()
+}
+
+#[test]
+fn echo_alpn_sni() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let cert = TestCert::generate();
+        let acceptor = TlsAcceptor::new(cert.server_config(&[b"h2", b"http/1.1"]));
+        let connector = TlsConnector::new(cert.client_config(&[b"http/1.1"]));
+
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let server = spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            let mut stream = acceptor.accept(socket.into_stream()).await?;
+            assert_eq!(Some(&b"http/1.1"[..]), stream.alpn_protocol());
+            assert_eq!(Some("localhost"), stream.server_name());
+            let (mut reader, mut writer) = tokio::io::split(&mut stream);
+            tokio::io::copy(&mut reader, &mut writer).await?;
+            stream.shutdown().await?;
+            Ok::<_, io::Error>(())
+        });
+
+        let socket = TcpSocket::connect(addr).await?;
+        let mut stream = connector.connect(localhost(), socket.into_stream()).await?;
+        assert_eq!(Some(&b"http/1.1"[..]), stream.alpn_protocol());
+        assert_eq!(None, stream.server_name());
+        assert_eq!(addr, stream.peer_addr()?);
+
+        let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        stream.write_all(&data).await?;
+        stream.flush().await?;
+        let mut received = vec![0; data.len()];
+        stream.read_exact(&mut received).await?;
+        assert!(data == received);
+        stream.shutdown().await?;
+        server.await??;
+        Ok(())
+    })
+}
+
+#[test]
+fn completion_stream() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let cert = TestCert::generate();
+        let acceptor = TlsAcceptor::new(cert.server_config(&[]));
+        let connector = TlsConnector::new(cert.client_config(&[]));
+        let ring = BufRing::builder(6).buf_cnt(8).buf_len(4096).build()?;
+
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let server_ring = ring.clone();
+        let server = spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            let stream = socket.into_completion_stream(&server_ring);
+            let mut stream = acceptor.accept(stream).await?;
+            assert_eq!(None, stream.alpn_protocol());
+            stream.write_all(b"hello").await?;
+            stream.shutdown().await?;
+            Ok::<_, io::Error>(())
+        });
+
+        let socket = TcpSocket::connect(addr).await?;
+        let stream = socket.into_completion_stream(&ring);
+        let mut stream = connector.connect(localhost(), stream).await?;
+        let mut received = vec![];
+        stream.read_to_end(&mut received).await?;
+        assert_eq!(b"hello", &received[..]);
+        server.await??;
+        Ok(())
+    })
+}
+
+#[test]
+fn session_resumption() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let cert = TestCert::generate();
+        let acceptor = TlsAcceptor::new(cert.server_config(&[]));
+        let connector = TlsConnector::new(cert.client_config(&[]));
+
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let server = spawn(async move {
+            let mut resumed = vec![];
+            for _ in 0..2 {
+                let (socket, _) = listener.accept().await?;
+                let mut stream = acceptor.accept(socket.into_stream()).await?;
+                resumed.push(stream.is_resumed());
+                stream.write_all(b"hello").await?;
+                stream.shutdown().await?;
+            }
+            Ok::<_, io::Error>(resumed)
+        });
+
+        let mut resumed = vec![];
+        for _ in 0..2 {
+            let socket = TcpSocket::connect(addr).await?;
+            let mut stream = connector.connect(localhost(), socket.into_stream()).await?;
+            resumed.push(stream.is_resumed());
+            // Reading processes the session tickets sent after the handshake.
+            let mut received = vec![];
+            stream.read_to_end(&mut received).await?;
+        }
+        assert_eq!(vec![false, true], resumed);
+        assert_eq!(vec![false, true], server.await??);
+        Ok(())
+    })
+}
+
+#[test]
+fn untrusted_certificate() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let cert = TestCert::generate();
+        let other = TestCert::generate();
+        let acceptor = TlsAcceptor::new(cert.server_config(&[]));
+        let connector = TlsConnector::new(other.client_config(&[]));
+
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let server = spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            acceptor.accept(socket.into_stream()).await.map(|_| ())
+        });
+
+        let socket = TcpSocket::connect(addr).await?;
+        let err = connector
+            .connect(localhost(), socket.into_stream())
+            .await
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert!(server.await?.is_err());
+        Ok(())
+    })
+}
@@ -10,6 +10,11 @@
 use std::cmp;
 
 use bytes::{Bytes, BytesMut};
+use smallvec::SmallVec;
+
+mod aligned;
+
+pub use aligned::AlignedBuf;
 
 /// [`StableBuf`] is a trait for types which expose a
 /// stable pointer into initialized memory.
@@ -179,6 +184,88 @@ unsafe impl StableBufMut for BytesMut {
     }
 }
 
+/// [`StableBufs`] is a trait for collections of [`StableBuf`]s, used
+/// for vectored writes.
+///
+/// The collection itself may move, only the memory referenced by each
+/// buffer is required to be stable.
+pub trait StableBufs: Unpin + 'static {
+    /// The buffer type contained in the collection.
+    type Buf: StableBuf;
+
+    /// Returns the buffers as a slice.
+    fn as_bufs(&self) -> &[Self::Buf];
+}
+
+/// [`StableBufsMut`] is a trait for collections of [`StableBufMut`]s, used
+/// for vectored reads.
+///
+/// The collection itself may move, only the memory referenced by each
+/// buffer is required to be stable.
+pub trait StableBufsMut: Unpin + 'static {
+    /// The buffer type contained in the collection.
+    type Buf: StableBufMut;
+
+    /// Returns the buffers as a mutable slice.
+    fn as_bufs_mut(&mut self) -> &mut [Self::Buf];
+}
+
+impl<B: StableBuf> StableBufs for Vec<B> {
+    type Buf = B;
+
+    fn as_bufs(&self) -> &[B] {
+        self
+    }
+}
+
+impl<B: StableBufMut> StableBufsMut for Vec<B> {
+    type Buf = B;
+
+    fn as_bufs_mut(&mut self) -> &mut [B] {
+        self
+    }
+}
+
+impl<B: StableBuf, const N: usize> StableBufs for [B; N] {
+    type Buf = B;
+
+    fn as_bufs(&self) -> &[B] {
+        self
+    }
+}
+
+impl<B: StableBufMut, const N: usize> StableBufsMut for [B; N] {
+    type Buf = B;
+
+    fn as_bufs_mut(&mut self) -> &mut [B] {
+        self
+    }
+}
+
+impl<A> StableBufs for SmallVec<A>
+where
+    A: smallvec::Array + Unpin + 'static,
+    A::Item: StableBuf,
+{
+    type Buf = A::Item;
+
+    fn as_bufs(&self) -> &[A::Item] {
+        self
+    }
+}
+
+impl<A> StableBufsMut for SmallVec<A>
+where
+    A: smallvec::Array + Unpin + 'static,
+    A::Item: StableBufMut,
+{
+    type Buf = A::Item;
+
+    fn as_bufs_mut(&mut self) -> &mut [A::Item] {
+        self
+    }
+}
+
 /// [`BufCursor`] is a wrapper around a [`StableBuf`] which allows
 ///  for advancing the initialized bytes.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -187,10 +274,7 @@ pub struct BufCursor<B> {
     pos: usize,
 }
 
-impl<B> BufCursor<B>
-where
-    B: StableBuf,
-{
+impl<B> BufCursor<B> {
     /// Create a new [`BufCursor`] from a [`StableBuf`].
     ///
     /// The cursor is initialized at the start of the buffer.
@@ -225,6 +309,26 @@ where
     }
 }
 
+unsafe impl<B> StableBufMut for BufCursor<B>
+where
+    B: StableBufMut,
+{
+    fn stable_ptr_mut(&mut self) -> *mut u8 {
+        let offset = cmp::min(self.buf.bytes_remaining(), self.pos);
+        // Safety: `offset` is always in bounds as it is the min of the capacity
+        // and the current position.
+        unsafe { self.buf.stable_ptr_mut().add(offset) }
+    }
+
+    fn bytes_remaining(&self) -> usize {
+        self.buf.bytes_remaining().saturating_sub(self.pos)
+    }
+
+    unsafe fn set_init(&mut self, init_len: usize) {
+        self.buf.set_init(self.pos + init_len);
+    }
+}
+
 /// [`BufLimit`] is a wrapper around a [`StableBuf`] which limits
 /// the number of initialized bytes.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -233,14 +337,16 @@ pub struct BufLimit<B> {
     limit: usize,
 }
 
-impl<B> BufLimit<B>
-where
-    B: StableBuf,
-{
+impl<B> BufLimit<B> {
     /// Create a new [`BufLimit`] from a [`StableBuf`].
     pub fn new(buf: B, limit: usize) -> Self {
         Self { buf, limit }
     }
+
+    /// Return the inner [`StableBuf`].
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
 }
 
 unsafe impl<B> StableBuf for BufLimit<B>
@@ -255,3 +361,20 @@ where
         cmp::min(self.buf.bytes_init(), self.limit)
     }
 }
+
+unsafe impl<B> StableBufMut for BufLimit<B>
+where
+    B: StableBufMut,
+{
+    fn stable_ptr_mut(&mut self) -> *mut u8 {
+        self.buf.stable_ptr_mut()
+    }
+
+    fn bytes_remaining(&self) -> usize {
+        cmp::min(self.buf.bytes_remaining(), self.limit)
+    }
+
+    unsafe fn set_init(&mut self, init_len: usize) {
+        self.buf.set_init(init_len);
+    }
+}
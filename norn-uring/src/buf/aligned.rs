@@ -0,0 +1,176 @@
+use std::alloc::{self, Layout};
+use std::ptr::NonNull;
+use std::{fmt, io, ops};
+
+use crate::buf::{StableBuf, StableBufMut};
+use crate::fs::Metadata;
+
+/// [`AlignedBuf`] is a heap allocated buffer with a fixed alignment.
+///
+/// Files opened with [`OpenOptions::direct`](crate::fs::OpenOptions::direct)
+/// require that the memory, file offset and length of each I/O are aligned,
+/// and fail with `EINVAL` otherwise. The required alignment depends on the
+/// filesystem and device, and can be queried with
+/// [`Metadata::dio_mem_align`] and [`Metadata::dio_offset_align`], or used
+/// directly via [`AlignedBuf::for_direct_io`].
+pub struct AlignedBuf {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    len: usize,
+}
+
+impl fmt::Debug for AlignedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedBuf")
+            .field("len", &self.len)
+            .field("cap", &self.capacity())
+            .field("align", &self.alignment())
+            .finish()
+    }
+}
+
+impl AlignedBuf {
+    /// Allocate a new zeroed buffer with the provided capacity and alignment.
+    ///
+    /// The capacity is rounded up to a multiple of the alignment, so the
+    /// buffer is never empty.
+    ///
+    /// ### Panics
+    /// Panics if `align` is not a power of two, or if the rounded capacity
+    /// overflows `isize`.
+    pub fn new(capacity: usize, align: usize) -> AlignedBuf {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let size = capacity.max(1).next_multiple_of(align);
+        let layout = Layout::from_size_align(size, align).expect("invalid buffer layout");
+        // Safety: The layout has a non-zero size.
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        let Some(ptr) = NonNull::new(ptr) else {
+            alloc::handle_alloc_error(layout)
+        };
+        AlignedBuf {
+            ptr,
+            layout,
+            len: 0,
+        }
+    }
+
+    /// Allocate a new zeroed buffer suitable for O_DIRECT I/O on the file
+    /// described by `metadata`.
+    ///
+    /// The buffer is aligned to the larger of the memory and offset alignment
+    /// reported by statx, so its capacity is a valid O_DIRECT length. Returns
+    /// an error of kind [`io::ErrorKind::Unsupported`] if the kernel or
+    /// filesystem does not report DIO alignment.
+    pub fn for_direct_io(capacity: usize, metadata: &Metadata) -> io::Result<AlignedBuf> {
+        match (metadata.dio_mem_align(), metadata.dio_offset_align()) {
+            (Some(mem), Some(offset)) => Ok(AlignedBuf::new(capacity, mem.max(offset) as usize)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "O_DIRECT alignment is not reported for this file",
+            )),
+        }
+    }
+
+    /// Returns the number of initialized bytes in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer contains no initialized bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Returns the alignment of the buffer.
+    pub fn alignment(&self) -> usize {
+        self.layout.align()
+    }
+
+    /// Clear the buffer, setting the number of initialized bytes to zero.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Append the provided bytes to the buffer.
+    ///
+    /// ### Panics
+    /// Panics if there is not enough remaining capacity to hold `src`.
+    pub fn put_slice(&mut self, src: &[u8]) {
+        assert!(
+            self.capacity() - self.len >= src.len(),
+            "put_slice overflows buffer capacity"
+        );
+        // Safety: The destination is within the bounds of the allocation.
+        unsafe {
+            let dst = self.ptr.as_ptr().add(self.len);
+            std::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+        }
+        self.len += src.len();
+    }
+
+    /// Set the number of initialized bytes.
+    ///
+    /// As the buffer is zeroed on allocation, every byte up to the capacity
+    /// is initialized, so this can be used to pad the buffer to an aligned
+    /// length before writing.
+    ///
+    /// ### Panics
+    /// Panics if `len` is greater than the capacity.
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= self.capacity(), "set_len exceeds buffer capacity");
+        self.len = len;
+    }
+}
+
+impl Drop for AlignedBuf {
+    fn drop(&mut self) {
+        // Safety: The pointer was allocated with this layout.
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+impl ops::Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The first `len` bytes are initialized.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl ops::DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: The first `len` bytes are initialized, and the buffer is
+        // exclusively owned.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+unsafe impl StableBuf for AlignedBuf {
+    fn stable_ptr(&self) -> *const u8 {
+        self.ptr.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len
+    }
+}
+
+unsafe impl StableBufMut for AlignedBuf {
+    fn stable_ptr_mut(&mut self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    fn bytes_remaining(&self) -> usize {
+        self.capacity()
+    }
+
+    unsafe fn set_init(&mut self, init_len: usize) {
+        self.len = init_len;
+    }
+}
@@ -6,11 +6,13 @@
 use std::cell::Cell;
 use std::rc::Rc;
 use std::sync::atomic::{self, AtomicU16};
-use std::{fmt, io, ops, ptr};
+use std::{fmt, io, ops};
 
 use io_uring::types::{self, BufRingEntry};
 use io_uring::Submitter;
 
+use crate::util::mmap::AnonymousMmap;
+use crate::util::notify::{Notified, Notify};
 use crate::Handle;
 
 /// [`BufRing`] is a reference counted buffer ring which can be registered
@@ -62,6 +64,19 @@ impl BufRing {
     pub(crate) fn bgid(&self) -> Bgid {
         self.rc.bgid
     }
+
+    /// Returns the number of buffers which have not been handed out.
+    ///
+    /// This includes buffers which the kernel has selected for completions
+    /// which have not yet been processed.
+    pub fn available(&self) -> u16 {
+        self.rc.buf_cnt - self.rc.outstanding.get()
+    }
+
+    /// Returns a future which completes when a buffer is returned to the ring.
+    pub(crate) fn returned(&self) -> Notified<'_> {
+        self.rc.returned.wait()
+    }
 }
 
 /// [`BufRingBuf`] is a reference to a buffer in a buffer ring.
@@ -197,10 +212,7 @@ impl Builder {
         // larger than 2^15 anyway, so this is a good place to catch it. Here we return a unique
         // error that is more descriptive than the InvalidArg that would come from the interface.
         if b.ring_entries > (1 << 15) {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "ring_entries exceeded 32768",
-            ));
+            return Err(io::Error::other("ring_entries exceeded 32768"));
         }
 
         // Requirement of the interface is the ring entries is a power of two, making its and our
@@ -239,6 +251,13 @@ struct InnerBufRing {
     // value from time to time. The address could be computed from ring_start when needed. This
     // might be here for no good reason any more.
     shared_tail: *const AtomicU16,
+
+    // `outstanding` is the number of buffers currently handed out as a `BufRingBuf`.
+    outstanding: Cell<u16>,
+
+    // `returned` is notified whenever a buffer is returned to the ring, allowing operations
+    // which failed with ENOBUFS to wait before retrying.
+    returned: Notify,
 }
 
 impl InnerBufRing {
@@ -293,6 +312,8 @@ impl InnerBufRing {
             buf_list,
             local_tail: Cell::new(0),
             shared_tail,
+            outstanding: Cell::new(0),
+            returned: Notify::default(),
         };
 
         Ok(buf_ring)
@@ -309,8 +330,7 @@ impl InnerBufRing {
             match e.raw_os_error() {
                 Some(libc::EINVAL) => {
                     // using buf_ring requires kernel 5.19 or greater.
-                    return Err(io::Error::new(
-                            io::ErrorKind::Other,
+                    return Err(io::Error::other(
                             format!("buf_ring.register returned {}, most likely indicating this kernel is not 5.19+", e),
                             ));
                 }
@@ -319,8 +339,7 @@ impl InnerBufRing {
                     // operations that can remove the first, but care must be taken that there
                     // are no outstanding operations that will still return a buffer from that
                     // one.
-                    return Err(io::Error::new(
-                            io::ErrorKind::Other,
+                    return Err(io::Error::other(
                             format!(
                                 "buf_ring.register returned `{}`, indicating the attempted buffer group id {} was already registered",
                             e,
@@ -328,10 +347,10 @@ impl InnerBufRing {
                         ));
                 }
                 _ => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        format!("buf_ring.register returned `{}` for group id {}", e, bgid),
-                    ));
+                    return Err(io::Error::other(format!(
+                        "buf_ring.register returned `{}` for group id {}",
+                        e, bgid
+                    )));
                 }
             }
         };
@@ -354,6 +373,8 @@ impl InnerBufRing {
     unsafe fn dropping_bid(&self, bid: Bid) {
         self.buf_ring_push(bid);
         self.buf_ring_sync();
+        self.outstanding.set(self.outstanding.get() - 1);
+        self.returned.notify(usize::MAX);
     }
 
     // Returns the buffer group id.
@@ -373,6 +394,7 @@ impl InnerBufRing {
 
         assert!(len <= self.buf_len);
 
+        self.outstanding.set(self.outstanding.get() + 1);
         Ok(BufRingBuf::new(buf_ring, bid, len))
     }
 
@@ -434,59 +456,6 @@ impl Drop for InnerBufRing {
     }
 }
 
-/// An anonymous region of memory mapped using `mmap(2)`, not backed by a file
-/// but that is guaranteed to be page-aligned and zero-filled.
-struct AnonymousMmap {
-    addr: ptr::NonNull<libc::c_void>,
-    len: usize,
-}
-
-impl AnonymousMmap {
-    /// Creates a new anonymous mapping of `len` bytes.
-    fn new(len: usize) -> io::Result<Self> {
-        let addr = unsafe {
-            match libc::mmap(
-                ptr::null_mut(),
-                len,
-                libc::PROT_READ | libc::PROT_WRITE,
-                libc::MAP_ANONYMOUS | libc::MAP_PRIVATE | libc::MAP_POPULATE,
-                0,
-                0,
-            ) {
-                libc::MAP_FAILED => return Err(io::Error::last_os_error()),
-                addr => ptr::NonNull::new_unchecked(addr),
-            }
-        };
-        match unsafe { libc::madvise(addr.as_ptr(), len, libc::MADV_DONTFORK) } {
-            0 => {
-                let mmap = Self { addr, len };
-                Ok(mmap)
-            }
-            _ => Err(io::Error::last_os_error()),
-        }
-    }
-
-    /// Get a pointer to the memory.
-    #[inline]
-    fn as_ptr(&self) -> *const libc::c_void {
-        self.addr.as_ptr()
-    }
-
-    /// Get a mut pointer to the memory.
-    #[inline]
-    fn as_ptr_mut(&self) -> *mut libc::c_void {
-        self.addr.as_ptr()
-    }
-}
-
-impl Drop for AnonymousMmap {
-    fn drop(&mut self) {
-        unsafe {
-            libc::munmap(self.addr.as_ptr(), self.len);
-        }
-    }
-}
-
 impl ops::Deref for BufRingBuf {
     type Target = [u8];
 
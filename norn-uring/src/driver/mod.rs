@@ -385,12 +385,8 @@ impl Shared {
             self.submit(ParkMode::NoPark)?;
         }
         // Try again.
-        self.try_push_raw(entry).map_err(|err| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                format!("failed to push entry: {:?}", err),
-            )
-        })?;
+        self.try_push_raw(entry)
+            .map_err(|err| io::Error::other(format!("failed to push entry: {:?}", err)))?;
         Ok(())
     }
 
@@ -421,7 +417,16 @@ impl Shared {
                 let args = SubmitArgs::new();
                 submitter.submit_with_args(1, &args)?
             }
-            ParkMode::NoPark => submitter.submit()?,
+            ParkMode::NoPark => {
+                // Waiting with a zero timeout sets IORING_ENTER_GETEVENTS, without which
+                // rings set up with IORING_SETUP_DEFER_TASKRUN never post completions.
+                let ts = Timespec::new();
+                let args = SubmitArgs::new().timespec(&ts);
+                match submitter.submit_with_args(1, &args) {
+                    Err(err) if err.raw_os_error() == Some(libc::ETIME) => 0,
+                    res => res?,
+                }
+            }
         };
         self.backpressure.notify(submitted);
         Ok(submitted)
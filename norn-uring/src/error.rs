@@ -23,7 +23,7 @@ enum SubmitErrorKind {
 impl From<SubmitError> for io::Error {
     fn from(value: SubmitError) -> Self {
         match value.kind {
-            SubmitErrorKind::ShuttingDown => io::Error::new(io::ErrorKind::Other, value),
+            SubmitErrorKind::ShuttingDown => io::Error::other(value),
         }
     }
 }
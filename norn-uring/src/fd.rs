@@ -68,6 +68,38 @@ impl NornFd {
         &self.inner.kind
     }
 
+    /// Returns the raw file descriptor.
+    ///
+    /// Fixed file descriptors have no raw file descriptor, `None` is returned.
+    pub(crate) fn as_raw_fd(&self) -> Option<RawFd> {
+        match self.inner.kind {
+            FdKind::Fd(fd) => Some(fd.0),
+            FdKind::Fixed(_) => None,
+        }
+    }
+
+    /// Releases ownership of the raw file descriptor.
+    ///
+    /// Closing the file descriptor becomes the responsibility of the caller.
+    /// Ownership can only be released from the last reference, otherwise an
+    /// operation holding another reference could use the file descriptor after
+    /// the caller closed it. Fixed file descriptors can not be released. In
+    /// both cases `self` is returned.
+    pub(crate) fn into_raw_fd(self) -> Result<RawFd, NornFd> {
+        match self.as_raw_fd() {
+            Some(fd) if Rc::strong_count(&self.inner) == 1 => {
+                self.inner.closed.set(true);
+                Ok(fd)
+            }
+            _ => Err(self),
+        }
+    }
+
+    /// Returns true if both refer to the same file descriptor.
+    pub(crate) fn same_fd(&self, other: &NornFd) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
     pub(crate) async fn close(&self) -> io::Result<()> {
         loop {
             if self.inner.closed.get() {
@@ -0,0 +1,322 @@
+//! Support for io_uring registered (fixed) buffers.
+//!
+//! Registering buffers with `io_uring_register_buffers` allows the kernel
+//! to pin the backing pages once, rather than mapping them for every
+//! operation. This is most useful for O_DIRECT storage I/O, where the
+//! per-operation page pinning can dominate the cost of small reads and
+//! writes.
+//!
+//! Only a single set of buffers can be registered with a ring at a time,
+//! so only one [`FixedBufPool`] may be alive per driver.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::{fmt, io, ops};
+
+use io_uring::Submitter;
+
+use crate::buf::{StableBuf, StableBufMut};
+use crate::util::mmap::AnonymousMmap;
+use crate::util::notify::Notify;
+use crate::Handle;
+
+/// [`FixedBufPool`] is a reference counted pool of buffers which are
+/// registered with io_uring.
+///
+/// Buffers are checked out of the pool with [`FixedBufPool::try_next`] or
+/// [`FixedBufPool::next`], and returned to the pool when dropped.
+#[derive(Clone)]
+pub struct FixedBufPool {
+    // The pool is reference counted because each buffer handed out has a reference back to
+    // the pool, keeping the registration alive until every buffer has been returned.
+    rc: Rc<InnerPool>,
+}
+
+impl fmt::Debug for FixedBufPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBufPool")
+            .field("buf_cnt", &self.rc.buf_cnt)
+            .field("buf_len", &self.rc.buf_len)
+            .field("available", &self.rc.free.borrow().len())
+            .finish()
+    }
+}
+
+impl FixedBufPool {
+    /// Create a new [`Builder`] for a [`FixedBufPool`].
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Returns the capacity of each buffer in the pool.
+    pub fn buf_capacity(&self) -> usize {
+        self.rc.buf_len
+    }
+
+    /// Returns the number of buffers in the pool.
+    pub fn buf_count(&self) -> u16 {
+        self.rc.buf_cnt
+    }
+
+    /// Returns the number of buffers which are currently available.
+    pub fn available(&self) -> usize {
+        self.rc.free.borrow().len()
+    }
+
+    /// Take a buffer from the pool.
+    ///
+    /// Returns `None` if all buffers are currently checked out.
+    pub fn try_next(&self) -> Option<FixedBuf> {
+        let index = self.rc.free.borrow_mut().pop()?;
+        Some(FixedBuf::new(self.clone(), index))
+    }
+
+    /// Take a buffer from the pool, waiting for one to be returned
+    /// if all buffers are currently checked out.
+    pub async fn next(&self) -> FixedBuf {
+        loop {
+            if let Some(buf) = self.try_next() {
+                return buf;
+            }
+            self.rc.notify.wait().await;
+        }
+    }
+}
+
+/// [`FixedBuf`] is a buffer checked out from a [`FixedBufPool`].
+///
+/// The buffer is returned to the pool when dropped. Fixed buffers can be
+/// used with the `*_fixed` operations, such as
+/// [`File::read_fixed_at`](crate::fs::File::read_fixed_at), as well as any
+/// operation accepting a [`StableBuf`] or [`StableBufMut`].
+pub struct FixedBuf {
+    pool: FixedBufPool,
+    index: u16,
+    len: usize,
+}
+
+impl fmt::Debug for FixedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBuf")
+            .field("index", &self.index)
+            .field("len", &self.len)
+            .field("cap", &self.capacity())
+            .finish()
+    }
+}
+
+impl FixedBuf {
+    fn new(pool: FixedBufPool, index: u16) -> Self {
+        Self {
+            pool,
+            index,
+            len: 0,
+        }
+    }
+
+    /// Returns the index of this buffer in the registered buffer table.
+    pub(crate) fn buf_index(&self) -> u16 {
+        self.index
+    }
+
+    /// Returns the number of initialized bytes in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer contains no initialized bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.pool.rc.buf_len
+    }
+
+    /// Clear the buffer, setting the number of initialized bytes to zero.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Append the provided bytes to the buffer.
+    ///
+    /// ### Panics
+    /// Panics if there is not enough remaining capacity to hold `src`.
+    pub fn put_slice(&mut self, src: &[u8]) {
+        assert!(
+            self.capacity() - self.len >= src.len(),
+            "put_slice overflows buffer capacity"
+        );
+        // Safety: The destination is within the bounds of this buffer, which is
+        // exclusively owned by this FixedBuf.
+        unsafe {
+            let dst = self.pool.rc.ptr(self.index).add(self.len);
+            std::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+        }
+        self.len += src.len();
+    }
+}
+
+impl Drop for FixedBuf {
+    fn drop(&mut self) {
+        self.pool.rc.free.borrow_mut().push(self.index);
+        self.pool.rc.notify.notify(1);
+    }
+}
+
+impl ops::Deref for FixedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl ops::DerefMut for FixedBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let ptr = self.pool.rc.ptr(self.index);
+        // Safety: The memory is zero-filled on allocation, so every byte up to the
+        // capacity is initialized.
+        unsafe { std::slice::from_raw_parts_mut(ptr, self.len) }
+    }
+}
+
+unsafe impl StableBuf for FixedBuf {
+    fn stable_ptr(&self) -> *const u8 {
+        self.pool.rc.ptr(self.index)
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len
+    }
+}
+
+unsafe impl StableBufMut for FixedBuf {
+    fn stable_ptr_mut(&mut self) -> *mut u8 {
+        self.pool.rc.ptr(self.index)
+    }
+
+    fn bytes_remaining(&self) -> usize {
+        self.capacity()
+    }
+
+    unsafe fn set_init(&mut self, init_len: usize) {
+        self.len = init_len;
+    }
+}
+
+/// [`Builder`] is used to create a new [`FixedBufPool`].
+#[derive(Copy, Clone, Debug)]
+pub struct Builder {
+    buf_cnt: u16,
+    buf_len: usize,
+}
+
+impl Builder {
+    fn new() -> Builder {
+        Builder {
+            buf_cnt: 16,
+            buf_len: 4096,
+        }
+    }
+
+    /// The number of buffers to allocate.
+    pub fn buf_cnt(mut self, buf_cnt: u16) -> Builder {
+        self.buf_cnt = buf_cnt;
+        self
+    }
+
+    /// The length to be preallocated for each buffer.
+    ///
+    /// Buffers are carved out of a single page aligned allocation. Using a
+    /// multiple of the page size keeps every buffer suitably aligned for
+    /// O_DIRECT I/O.
+    pub fn buf_len(mut self, buf_len: usize) -> Builder {
+        self.buf_len = buf_len;
+        self
+    }
+
+    /// Allocate the buffers and register them with the current driver.
+    pub fn build(&self) -> io::Result<FixedBufPool> {
+        let inner = InnerPool::new(self.buf_cnt, self.buf_len)?;
+        inner.handle.with_submitter(|s| inner.register(s))?;
+        Ok(FixedBufPool { rc: Rc::new(inner) })
+    }
+}
+
+struct InnerPool {
+    // `handle` keeps the ring alive so the buffers can be unregistered on drop,
+    // even outside of the driver context.
+    handle: Handle,
+    buf_cnt: u16,
+    buf_len: usize,
+    // `memory` holds all buffers back to back, buffer `i` starts at `i * buf_len`.
+    memory: AnonymousMmap,
+    free: RefCell<Vec<u16>>,
+    notify: Notify,
+    // `registered` is set once the buffers have been successfully registered, the
+    // buffers must only be unregistered if this pool was the one to register them.
+    registered: Cell<bool>,
+}
+
+impl InnerPool {
+    fn new(buf_cnt: u16, buf_len: usize) -> io::Result<InnerPool> {
+        if buf_cnt == 0 || buf_len == 0 {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+        let len = buf_len
+            .checked_mul(buf_cnt as usize)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let memory = AnonymousMmap::new(len)?;
+        // Hand out low indices first.
+        let free = (0..buf_cnt).rev().collect();
+        Ok(InnerPool {
+            handle: Handle::current(),
+            buf_cnt,
+            buf_len,
+            memory,
+            free: RefCell::new(free),
+            notify: Notify::default(),
+            registered: Cell::new(false),
+        })
+    }
+
+    fn register(&self, submitter: &Submitter<'_>) -> io::Result<()> {
+        let iovecs: Vec<libc::iovec> = (0..self.buf_cnt)
+            .map(|index| libc::iovec {
+                iov_base: self.ptr(index) as _,
+                iov_len: self.buf_len,
+            })
+            .collect();
+        // Safety: The memory backing the iovecs lives as long as the pool, and the
+        // buffers are unregistered before the memory is unmapped.
+        let res = unsafe { submitter.register_buffers(&iovecs) };
+        if let Err(e) = res {
+            if e.raw_os_error() == Some(libc::EBUSY) {
+                return Err(io::Error::other(format!(
+                    "register_buffers returned `{}`, indicating buffers are already registered with this ring",
+                    e
+                )));
+            }
+            return Err(e);
+        }
+        self.registered.set(true);
+        Ok(())
+    }
+
+    fn ptr(&self, index: u16) -> *mut u8 {
+        assert!(index < self.buf_cnt);
+        // Safety: `index` is within bounds of the allocation.
+        unsafe { (self.memory.as_ptr_mut() as *mut u8).add(index as usize * self.buf_len) }
+    }
+}
+
+impl Drop for InnerPool {
+    fn drop(&mut self) {
+        if self.registered.get() {
+            if let Err(err) = self.handle.with_submitter(|s| s.unregister_buffers()) {
+                log::warn!(target: "norn_uring::fixedbuf", "unregister_buffers.failed: {}", err);
+            }
+        }
+    }
+}
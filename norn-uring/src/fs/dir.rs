@@ -1,19 +1,173 @@
+use std::ffi::CString;
+use std::future::poll_fn;
 use std::io;
-use std::path::Path;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 
+use futures_core::Stream;
+use io_uring::types::FsyncFlags;
 use io_uring::{opcode, types};
 
+use crate::fd::{FdKind, NornFd};
+use crate::fs::file::{Open, Sync};
+use crate::fs::metadata::{metadata, symlink_metadata, Metadata, Statx};
+use crate::fs::read_dir::ReadDir;
+use crate::fs::{opts, File};
 use crate::operation::{Operation, Singleshot};
 use crate::Handle;
 
+/// A handle to an open directory.
+///
+/// Paths passed to the methods on [`Dir`] are resolved relative to the
+/// directory, rather than the current working directory. Holding a [`Dir`]
+/// open ensures that operations continue to target the same directory even
+/// if it is renamed.
+pub struct Dir {
+    fd: NornFd,
+    handle: Handle,
+}
+
+impl std::fmt::Debug for Dir {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dir").finish()
+    }
+}
+
+impl Dir {
+    /// Open the directory at the provided path.
+    pub async fn open<P: AsRef<Path>>(path: P) -> io::Result<Dir> {
+        Self::open_at(None, path.as_ref()).await
+    }
+
+    async fn open_at(dir: Option<NornFd>, path: &Path) -> io::Result<Dir> {
+        let open = Open::new(dir, path, libc::O_RDONLY | libc::O_DIRECTORY, 0, 0)?;
+        let handle = Handle::current();
+        let fd = handle.submit(open).await?;
+        Ok(Dir { fd, handle })
+    }
+
+    /// Open a subdirectory relative to this directory.
+    pub async fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Dir> {
+        Self::open_at(Some(self.fd.clone()), path.as_ref()).await
+    }
+
+    /// Open a file relative to this directory with the provided options.
+    pub async fn open_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        opts: &opts::OpenOptions,
+    ) -> io::Result<File> {
+        File::open_at(Some(self.fd.clone()), path.as_ref(), *opts).await
+    }
+
+    /// Create a directory relative to this directory.
+    pub async fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let create = MkDirAt::new(Some(self.fd.clone()), path.as_ref(), 0o777)?;
+        self.handle.submit(create).await
+    }
+
+    /// Remove a file relative to this directory.
+    pub async fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let remove = UnlinkAt::new(Some(self.fd.clone()), path.as_ref(), 0)?;
+        self.handle.submit(remove).await
+    }
+
+    /// Remove an empty directory relative to this directory.
+    pub async fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let remove = UnlinkAt::new(Some(self.fd.clone()), path.as_ref(), libc::AT_REMOVEDIR)?;
+        self.handle.submit(remove).await
+    }
+
+    /// Rename `from` to `to`, both relative to this directory.
+    ///
+    /// `to` is replaced if it already exists.
+    pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        self.rename_into(from, self, to).await
+    }
+
+    /// Rename `from`, relative to this directory, to `to`, relative to `to_dir`.
+    ///
+    /// `to` is replaced if it already exists.
+    pub async fn rename_into<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &Dir,
+        to: Q,
+    ) -> io::Result<()> {
+        let rename = RenameAt::new(
+            Some(self.fd.clone()),
+            from.as_ref(),
+            Some(to_dir.fd.clone()),
+            to.as_ref(),
+            0,
+        )?;
+        self.handle.submit(rename).await
+    }
+
+    /// Query the metadata of a path relative to this directory.
+    ///
+    /// Symlinks are followed.
+    pub async fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let flags = libc::AT_STATX_SYNC_AS_STAT;
+        let statx = Statx::new_at(Some(self.fd.clone()), path.as_ref(), flags)?;
+        self.handle.submit(statx).await
+    }
+
+    /// Query the metadata of a path relative to this directory.
+    ///
+    /// Symlinks are not followed.
+    pub async fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let flags = libc::AT_STATX_SYNC_AS_STAT | libc::AT_SYMLINK_NOFOLLOW;
+        let statx = Statx::new_at(Some(self.fd.clone()), path.as_ref(), flags)?;
+        self.handle.submit(statx).await
+    }
+
+    /// Returns a stream over the entries in this directory.
+    ///
+    /// The `.` and `..` entries are skipped. Like [`read_dir`], polling the
+    /// stream blocks the thread while the entries are read.
+    pub async fn read_dir(&self) -> io::Result<ReadDir> {
+        // Open a new file descriptor so that each stream has its own offset.
+        let dir = Self::open_at(Some(self.fd.clone()), Path::new(".")).await?;
+        Ok(ReadDir::new(dir.fd))
+    }
+
+    /// Sync the directory to disk.
+    ///
+    /// This is required to make the creation, removal or renaming of entries
+    /// in the directory durable.
+    pub async fn sync(&self) -> io::Result<()> {
+        let sync = Sync::new(self.fd.clone(), FsyncFlags::empty());
+        self.handle.submit(sync).await
+    }
+
+    /// Close the directory.
+    pub async fn close(self) -> io::Result<()> {
+        self.fd.close().await
+    }
+}
+
+/// Returns a stream over the entries in the directory at the provided path.
+///
+/// The `.` and `..` entries are skipped.
+///
+/// This blocks. io_uring has no operation for reading directory entries, so
+/// polling the stream makes a blocking `getdents64` call, which stalls every
+/// task on the thread, whenever it runs out of buffered entries. The stream
+/// yields to the executor between these calls.
+pub async fn read_dir<P: AsRef<Path>>(path: P) -> io::Result<ReadDir> {
+    let dir = Dir::open(path).await?;
+    Ok(ReadDir::new(dir.fd))
+}
+
 /// Remove a file from the filesystem.
 ///
 /// This is equivalent to unlinkat.
 pub async fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
     let path = path.as_ref().to_owned();
     let handle = Handle::current();
-    let remove = UnlinkAt::new(&path)?;
+    let remove = UnlinkAt::new(None, &path, 0)?;
     handle.submit(remove).await?;
     Ok(())
 }
@@ -22,31 +176,202 @@ pub async fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
 pub async fn create_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
     let path = path.as_ref().to_owned();
     let handle = Handle::current();
-    let create = MkDirAt::new(&path, 0o777)?;
+    let create = MkDirAt::new(None, &path, 0o777)?;
     handle.submit(create).await?;
     Ok(())
 }
 
-/// Remove a directory from the filesystem.
+/// Recursively create a directory and all of its missing parents.
+///
+/// Directories which already exist are skipped.
+pub async fn create_dir_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    // Walk up the path until we find an ancestor that exists, recording
+    // each component that needs to be created.
+    let mut missing = vec![];
+    let mut current = path;
+    loop {
+        if current == Path::new("") {
+            break;
+        }
+        match create_dir(current).await {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                missing.push(current);
+                match current.parent() {
+                    Some(parent) => current = parent,
+                    None => return Err(err),
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                if is_dir(current).await {
+                    break;
+                }
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    for dir in missing.into_iter().rev() {
+        match create_dir(dir).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && is_dir(dir).await => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Remove an empty directory from the filesystem.
+///
+/// This is equivalent to unlinkat with `AT_REMOVEDIR`.
 pub async fn remove_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
     let path = path.as_ref().to_owned();
     let handle = Handle::current();
-    let remove = UnlinkAt::new(&path)?;
+    let remove = UnlinkAt::new(None, &path, libc::AT_REMOVEDIR)?;
     handle.submit(remove).await?;
     Ok(())
 }
 
+/// Remove a directory and all of its contents.
+///
+/// Symlinks are removed rather than followed.
+///
+/// This blocks. Every directory in the tree is listed with [`read_dir`], which
+/// blocks the thread while reading entries, only the removals are submitted to
+/// the ring.
+pub async fn remove_dir_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref().to_owned();
+    if symlink_metadata(&path).await?.is_symlink() {
+        return remove_file(path).await;
+    }
+    // Directories are pushed twice, once to remove their contents and once
+    // more to remove the directory itself after its children.
+    let mut stack: Vec<(PathBuf, bool)> = vec![(path, false)];
+    while let Some((dir, emptied)) = stack.pop() {
+        if emptied {
+            remove_dir(&dir).await?;
+            continue;
+        }
+        stack.push((dir.clone(), true));
+        let mut entries = read_dir(&dir).await?;
+        while let Some(entry) = poll_fn(|cx| Pin::new(&mut entries).poll_next(cx)).await {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                stack.push((path, false));
+            } else {
+                remove_file(path).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rename a file or directory, replacing `to` if it already exists.
+///
+/// This is equivalent to renameat.
+pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    rename_with_flags(from.as_ref(), to.as_ref(), 0).await
+}
+
+/// Rename a file or directory, failing if `to` already exists.
+///
+/// This is equivalent to renameat2 with `RENAME_NOREPLACE`.
+pub async fn rename_noreplace<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    rename_with_flags(from.as_ref(), to.as_ref(), libc::RENAME_NOREPLACE).await
+}
+
+/// Atomically exchange two paths. Both paths must exist.
+///
+/// This is equivalent to renameat2 with `RENAME_EXCHANGE`.
+pub async fn rename_exchange<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> io::Result<()> {
+    rename_with_flags(a.as_ref(), b.as_ref(), libc::RENAME_EXCHANGE).await
+}
+
+async fn rename_with_flags(from: &Path, to: &Path, flags: u32) -> io::Result<()> {
+    let handle = Handle::current();
+    let rename = RenameAt::new(None, from, None, to, flags)?;
+    handle.submit(rename).await?;
+    Ok(())
+}
+
+/// Create a new hard link at `link` pointing to `original`.
+///
+/// This is equivalent to linkat.
+pub async fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+    let handle = Handle::current();
+    let op = LinkAt::new(original.as_ref(), link.as_ref())?;
+    handle.submit(op).await?;
+    Ok(())
+}
+
+/// Create a new symbolic link at `link` pointing to `original`.
+///
+/// This is equivalent to symlinkat.
+pub async fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Result<()> {
+    let handle = Handle::current();
+    let op = SymlinkAt::new(original.as_ref(), link.as_ref())?;
+    handle.submit(op).await?;
+    Ok(())
+}
+
+/// Returns true if the path refers to a directory.
+async fn is_dir(path: &Path) -> bool {
+    metadata(path).await.is_ok_and(|m| m.is_dir())
+}
+
+/// The directory file descriptor used to resolve relative paths.
+///
+/// Paths are resolved against the current working directory if no
+/// directory is provided.
+pub(crate) struct DirFd {
+    fd: types::Fd,
+    // Keeps the directory open until the operation completes.
+    _dir: Option<NornFd>,
+}
+
+impl DirFd {
+    /// Fixed files cannot be used as a directory, the `*at` operations only
+    /// accept regular file descriptors.
+    pub(crate) fn new(dir: Option<NornFd>) -> io::Result<Self> {
+        let fd = match dir.as_ref().map(NornFd::kind) {
+            None => types::Fd(libc::AT_FDCWD),
+            Some(FdKind::Fd(fd)) => *fd,
+            Some(FdKind::Fixed(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "fixed files cannot be used as a directory",
+                ))
+            }
+        };
+        Ok(Self { fd, _dir: dir })
+    }
+
+    pub(crate) fn fd(&self) -> types::Fd {
+        self.fd
+    }
+}
+
+/// Convert a path into a C string for passing to the kernel.
+///
+/// Paths are passed through as raw bytes, so they need not be valid UTF-8,
+/// but they must not contain interior NUL bytes.
+pub(crate) fn path_to_cstring(path: &Path) -> io::Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
 struct UnlinkAt {
-    path: std::ffi::CString,
+    dir: DirFd,
+    path: CString,
+    flags: i32,
 }
 
 impl UnlinkAt {
-    fn new(path: &Path) -> io::Result<Self> {
-        let path = path
-            .to_str()
-            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
-        let path = std::ffi::CString::new(path)?;
-        Ok(Self { path })
+    fn new(dir: Option<NornFd>, path: &Path, flags: i32) -> io::Result<Self> {
+        let dir = DirFd::new(dir)?;
+        let path = path_to_cstring(path)?;
+        Ok(Self { dir, path, flags })
     }
 }
 
@@ -54,7 +379,9 @@ impl Operation for UnlinkAt {
     fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
         let this = self.get_mut();
         let ptr = this.path.as_ptr();
-        opcode::UnlinkAt::new(types::Fd(libc::AT_FDCWD), ptr).build()
+        opcode::UnlinkAt::new(this.dir.fd(), ptr)
+            .flags(this.flags)
+            .build()
     }
 
     fn cleanup(&mut self, _: crate::operation::CQEResult) {}
@@ -69,17 +396,16 @@ impl Singleshot for UnlinkAt {
 }
 
 struct MkDirAt {
-    path: std::ffi::CString,
+    dir: DirFd,
+    path: CString,
     mode: u32,
 }
 
 impl MkDirAt {
-    fn new(path: &Path, mode: u32) -> io::Result<Self> {
-        let path = path
-            .to_str()
-            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
-        let path = std::ffi::CString::new(path)?;
-        Ok(Self { path, mode })
+    fn new(dir: Option<NornFd>, path: &Path, mode: u32) -> io::Result<Self> {
+        let dir = DirFd::new(dir)?;
+        let path = path_to_cstring(path)?;
+        Ok(Self { dir, path, mode })
     }
 }
 
@@ -87,7 +413,7 @@ impl Operation for MkDirAt {
     fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
         let this = self.get_mut();
         let ptr = this.path.as_ptr();
-        opcode::MkDirAt::new(types::Fd(libc::AT_FDCWD), ptr)
+        opcode::MkDirAt::new(this.dir.fd(), ptr)
             .mode(this.mode)
             .build()
     }
@@ -102,3 +428,116 @@ impl Singleshot for MkDirAt {
         result.result.map(drop)
     }
 }
+
+struct RenameAt {
+    from_dir: DirFd,
+    from: CString,
+    to_dir: DirFd,
+    to: CString,
+    flags: u32,
+}
+
+impl RenameAt {
+    fn new(
+        from_dir: Option<NornFd>,
+        from: &Path,
+        to_dir: Option<NornFd>,
+        to: &Path,
+        flags: u32,
+    ) -> io::Result<Self> {
+        let from_dir = DirFd::new(from_dir)?;
+        let from = path_to_cstring(from)?;
+        let to_dir = DirFd::new(to_dir)?;
+        let to = path_to_cstring(to)?;
+        Ok(Self {
+            from_dir,
+            from,
+            to_dir,
+            to,
+            flags,
+        })
+    }
+}
+
+impl Operation for RenameAt {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = self.get_mut();
+        let from_dir = this.from_dir.fd();
+        let to_dir = this.to_dir.fd();
+        opcode::RenameAt::new(from_dir, this.from.as_ptr(), to_dir, this.to.as_ptr())
+            .flags(this.flags)
+            .build()
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl Singleshot for RenameAt {
+    type Output = io::Result<()>;
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        result.result.map(drop)
+    }
+}
+
+struct LinkAt {
+    original: CString,
+    link: CString,
+}
+
+impl LinkAt {
+    fn new(original: &Path, link: &Path) -> io::Result<Self> {
+        let original = path_to_cstring(original)?;
+        let link = path_to_cstring(link)?;
+        Ok(Self { original, link })
+    }
+}
+
+impl Operation for LinkAt {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = self.get_mut();
+        let cwd = types::Fd(libc::AT_FDCWD);
+        opcode::LinkAt::new(cwd, this.original.as_ptr(), cwd, this.link.as_ptr()).build()
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl Singleshot for LinkAt {
+    type Output = io::Result<()>;
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        result.result.map(drop)
+    }
+}
+
+struct SymlinkAt {
+    original: CString,
+    link: CString,
+}
+
+impl SymlinkAt {
+    fn new(original: &Path, link: &Path) -> io::Result<Self> {
+        let original = path_to_cstring(original)?;
+        let link = path_to_cstring(link)?;
+        Ok(Self { original, link })
+    }
+}
+
+impl Operation for SymlinkAt {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = self.get_mut();
+        let cwd = types::Fd(libc::AT_FDCWD);
+        opcode::SymlinkAt::new(cwd, this.original.as_ptr(), this.link.as_ptr()).build()
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl Singleshot for SymlinkAt {
+    type Output = io::Result<()>;
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        result.result.map(drop)
+    }
+}
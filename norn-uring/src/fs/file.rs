@@ -1,19 +1,28 @@
-use std::io;
 use std::path::Path;
 use std::pin::Pin;
+use std::{cmp, io};
 
 use io_uring::types::FsyncFlags;
 use io_uring::{opcode, types};
+use smallvec::SmallVec;
 
-use crate::buf::{StableBuf, StableBufMut};
+use crate::buf::{BufCursor, StableBuf, StableBufMut, StableBufs, StableBufsMut};
 use crate::fd::{FdKind, NornFd};
+use crate::fixedbuf::FixedBuf;
+use crate::fs::dir::{path_to_cstring, DirFd};
+use crate::fs::metadata::{Metadata, Statx};
 use crate::fs::opts;
+use crate::fs::stream::FileStream;
 use crate::operation::{CQEResult, Operation, Singleshot};
+use crate::pipe::{PipeReader, PipeWriter, Splice};
 
 /// A reference to an open file on the filesystem.
 pub struct File {
     fd: NornFd,
     handle: crate::Handle,
+    // `dio_align` is only populated in debug builds for files opened with
+    // O_DIRECT, and is used to validate reads and writes before submission.
+    dio_align: Option<DioAlign>,
 }
 
 impl std::fmt::Debug for File {
@@ -28,13 +37,37 @@ impl File {
     pub(crate) async fn open_with_options<P: AsRef<Path>>(
         path: P,
         opts: opts::OpenOptions,
+    ) -> io::Result<Self> {
+        Self::open_at(None, path.as_ref(), opts).await
+    }
+
+    /// Open a file with the specified options at the provided path,
+    /// resolved relative to `dir`.
+    pub(crate) async fn open_at(
+        dir: Option<NornFd>,
+        path: &Path,
+        opts: opts::OpenOptions,
     ) -> io::Result<Self> {
         let access_mode = opts.get_access_mode()?;
         let creation_mode = opts.get_creation_mode()?;
-        let open = Open::new(path.as_ref(), access_mode, creation_mode)?;
+        let resolve = opts.get_resolve_flags();
+        let open = Open::new(dir, path, access_mode, creation_mode, resolve)?;
         let handle = crate::Handle::current();
         let fd = handle.submit(open).await?;
-        Ok(Self { fd, handle })
+        let mut dio_align = None;
+        if cfg!(debug_assertions) && opts.direct {
+            let statx = Statx::new_fd(fd.clone())?;
+            dio_align = handle
+                .submit(statx)
+                .await
+                .ok()
+                .and_then(|m| DioAlign::from_metadata(&m));
+        }
+        Ok(Self {
+            fd,
+            handle,
+            dio_align,
+        })
     }
 
     /// Open a file in read-only mode at the provided path.
@@ -43,6 +76,23 @@ impl File {
         opts.read(true).open(path).await
     }
 
+    /// Convert this file into a buffered stream.
+    ///
+    /// [`FileStream`] tracks a cursor into the file and implements the
+    /// tokio and futures `AsyncRead`, `AsyncWrite`, `AsyncSeek` and
+    /// `AsyncBufRead` traits. The stream starts at offset 0.
+    pub fn into_stream(self) -> FileStream {
+        FileStream::new(self)
+    }
+
+    pub(crate) fn fd(&self) -> &NornFd {
+        &self.fd
+    }
+
+    pub(crate) fn handle(&self) -> &crate::Handle {
+        &self.handle
+    }
+
     /// Returns a new [`OpenOptions`] object which can be used to open a file.
     pub fn with_options() -> opts::OpenOptions {
         opts::OpenOptions::new()
@@ -55,7 +105,10 @@ impl File {
     where
         B: StableBufMut + 'static,
     {
-        let read = ReadAt::new(self.fd.clone(), buf, offset);
+        let mut read = ReadAt::new(self.fd.clone(), buf, offset);
+        if let Err(err) = read.check_alignment(self.dio_align) {
+            return (Err(err), read.buf);
+        }
         self.handle.submit(read).await
     }
 
@@ -67,9 +120,160 @@ impl File {
         B: StableBuf + 'static,
     {
         let write = WriteAt::new(self.fd.clone(), buf, offset);
+        if let Err(err) = write.check_alignment(self.dio_align) {
+            return (Err(err), write.buf);
+        }
+        self.handle.submit(write).await
+    }
+
+    /// Read the exact number of bytes required to fill the buffer.
+    ///
+    /// The read will start at the provided offset. Short reads are resubmitted
+    /// until the buffer is full. If EOF is reached first, an error of kind
+    /// [`io::ErrorKind::UnexpectedEof`] is returned. The buffer is returned in
+    /// every case, with the bytes read so far marked as initialized.
+    pub async fn read_exact_at<B>(&self, buf: B, offset: u64) -> (io::Result<()>, B)
+    where
+        B: StableBufMut + 'static,
+    {
+        let mut cursor = BufCursor::new(buf);
+        let mut read = 0;
+        while cursor.bytes_remaining() > 0 {
+            let (res, c) = self.read_at(cursor, offset + read as u64).await;
+            cursor = c;
+            match res {
+                Ok(0) => {
+                    let err =
+                        io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer");
+                    return (Err(err), cursor.into_inner());
+                }
+                Ok(n) => {
+                    cursor.consume(n);
+                    read += n;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return (Err(err), cursor.into_inner()),
+            }
+        }
+        (Ok(()), cursor.into_inner())
+    }
+
+    /// Write the entire buffer to the file.
+    ///
+    /// The write will start at the provided offset. Short writes are resubmitted
+    /// until the buffer has been written. If the file stops accepting bytes, an
+    /// error of kind [`io::ErrorKind::WriteZero`] is returned. The buffer is
+    /// returned in every case.
+    pub async fn write_all_at<B>(&self, buf: B, offset: u64) -> (io::Result<()>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        let mut cursor = BufCursor::new(buf);
+        let mut written = 0;
+        while cursor.bytes_init() > 0 {
+            let (res, c) = self.write_at(cursor, offset + written as u64).await;
+            cursor = c;
+            match res {
+                Ok(0) => {
+                    let err =
+                        io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer");
+                    return (Err(err), cursor.into_inner());
+                }
+                Ok(n) => {
+                    cursor.consume(n);
+                    written += n;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return (Err(err), cursor.into_inner()),
+            }
+        }
+        (Ok(()), cursor.into_inner())
+    }
+
+    /// Read bytes from the file into the specified buffers.
+    ///
+    /// The read will start at the provided offset and fill the buffers in
+    /// order. Ownership of all buffers is returned once the read completes.
+    pub async fn readv_at<V>(&self, bufs: V, offset: u64) -> (io::Result<usize>, V)
+    where
+        V: StableBufsMut,
+    {
+        let mut read = ReadvAt::new(self.fd.clone(), bufs, offset);
+        if let Err(err) = read.check_alignment(self.dio_align) {
+            return (Err(err), read.bufs);
+        }
+        self.handle.submit(read).await
+    }
+
+    /// Write the specified buffers to the file.
+    ///
+    /// The write will start at the provided offset and write the buffers in
+    /// order. Ownership of all buffers is returned once the write completes.
+    pub async fn writev_at<V>(&self, bufs: V, offset: u64) -> (io::Result<usize>, V)
+    where
+        V: StableBufs,
+    {
+        let write = WritevAt::new(self.fd.clone(), bufs, offset);
+        if let Err(err) = write.check_alignment(self.dio_align) {
+            return (Err(err), write.bufs);
+        }
+        self.handle.submit(write).await
+    }
+
+    /// Splice up to `len` bytes from the file into a pipe.
+    ///
+    /// The read from the file will start at the provided offset. Returns the
+    /// number of bytes moved, 0 indicates EOF.
+    pub async fn splice_to(&self, offset: u64, pipe: &PipeWriter, len: u32) -> io::Result<usize> {
+        let splice = Splice::new(self.fd.clone(), offset as i64, pipe.fd().clone(), -1, len);
+        self.handle.submit(splice).await
+    }
+
+    /// Splice up to `len` bytes from a pipe into the file.
+    ///
+    /// The write to the file will start at the provided offset. Returns the
+    /// number of bytes moved.
+    pub async fn splice_from(&self, pipe: &PipeReader, offset: u64, len: u32) -> io::Result<usize> {
+        let splice = Splice::new(pipe.fd().clone(), -1, self.fd.clone(), offset as i64, len);
+        self.handle.submit(splice).await
+    }
+
+    /// Read bytes from the file into the specified registered buffer.
+    ///
+    /// The read will start at the provided offset. This is equivalent to
+    /// [`File::read_at`], but avoids the kernel pinning the buffer pages
+    /// for each operation.
+    pub async fn read_fixed_at(&self, buf: FixedBuf, offset: u64) -> (io::Result<usize>, FixedBuf) {
+        let mut read = ReadFixedAt::new(self.fd.clone(), buf, offset);
+        if let Err(err) = read.check_alignment(self.dio_align) {
+            return (Err(err), read.buf);
+        }
+        self.handle.submit(read).await
+    }
+
+    /// Write the specified registered buffer to the file.
+    ///
+    /// The write will start at the provided offset. This is equivalent to
+    /// [`File::write_at`], but avoids the kernel pinning the buffer pages
+    /// for each operation.
+    pub async fn write_fixed_at(
+        &self,
+        buf: FixedBuf,
+        offset: u64,
+    ) -> (io::Result<usize>, FixedBuf) {
+        let write = WriteFixedAt::new(self.fd.clone(), buf, offset);
+        if let Err(err) = write.check_alignment(self.dio_align) {
+            return (Err(err), write.buf);
+        }
         self.handle.submit(write).await
     }
 
+    /// Query the metadata of the file.
+    pub async fn metadata(&self) -> io::Result<Metadata> {
+        let statx = Statx::new_fd(self.fd.clone())?;
+        self.handle.submit(statx).await
+    }
+
     /// Sync the file and metadata to disk.
     pub async fn sync(&self) -> io::Result<()> {
         let flags = FsyncFlags::empty();
@@ -125,32 +329,100 @@ impl File {
     }
 }
 
-struct Open {
+/// The alignment required for O_DIRECT I/O on a file.
+#[derive(Clone, Copy, Debug)]
+struct DioAlign {
+    mem: u32,
+    offset: u32,
+}
+
+impl DioAlign {
+    fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        Some(Self {
+            mem: metadata.dio_mem_align()?,
+            offset: metadata.dio_offset_align()?,
+        })
+    }
+
+    fn check(&self, op: &str, ptr: *const u8, len: usize, offset: u64) -> io::Result<()> {
+        let misaligned = |what: &str, value: u64, align: u32| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "O_DIRECT {op}: {what} {value:#x} is not a multiple of the required alignment of {align} bytes"
+                ),
+            )
+        };
+        if !(ptr as u64).is_multiple_of(self.mem as u64) {
+            return Err(misaligned("buffer address", ptr as u64, self.mem));
+        }
+        if !offset.is_multiple_of(self.offset as u64) {
+            return Err(misaligned("file offset", offset, self.offset));
+        }
+        if !(len as u64).is_multiple_of(self.offset as u64) {
+            return Err(misaligned("length", len as u64, self.offset));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct Open {
+    dir: DirFd,
     path: std::ffi::CString,
     access_mode: i32,
     creation_mode: i32,
+    // `how` is only used when resolve flags are set, in which case the file is
+    // opened with openat2.
+    how: Option<types::OpenHow>,
 }
 
 impl Open {
-    fn new(path: &Path, access_mode: i32, creation_mode: i32) -> io::Result<Self> {
-        let path = path
-            .to_str()
-            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
-        let path = std::ffi::CString::new(path)?;
+    pub(crate) fn new(
+        dir: Option<NornFd>,
+        path: &Path,
+        access_mode: i32,
+        creation_mode: i32,
+        resolve: u64,
+    ) -> io::Result<Self> {
+        let dir = DirFd::new(dir)?;
+        let path = path_to_cstring(path)?;
+        let how = (resolve != 0).then(|| {
+            types::OpenHow::new()
+                .flags((access_mode | creation_mode | libc::O_CLOEXEC) as u64)
+                .mode(Self::mode(creation_mode) as u64)
+                .resolve(resolve)
+        });
         Ok(Self {
+            dir,
             path,
             access_mode,
             creation_mode,
+            how,
         })
     }
+
+    /// Returns the permission bits used if the file is created.
+    ///
+    /// The kernel rejects a non-zero mode when the file is not being created.
+    fn mode(creation_mode: i32) -> libc::mode_t {
+        if creation_mode & libc::O_CREAT != 0 {
+            0o666
+        } else {
+            0
+        }
+    }
 }
 
 impl Operation for Open {
     fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
         let this = self.get_mut();
         let ptr = this.path.as_ptr();
-        opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), ptr)
+        if let Some(how) = this.how.as_ref() {
+            return opcode::OpenAt2::new(this.dir.fd(), ptr, how).build();
+        }
+        opcode::OpenAt::new(this.dir.fd(), ptr)
             .flags(this.access_mode | this.creation_mode | libc::O_CLOEXEC)
+            .mode(Self::mode(this.creation_mode))
             .build()
     }
 
@@ -171,18 +443,34 @@ impl Singleshot for Open {
 }
 
 #[derive(Debug)]
-struct ReadAt<B> {
+pub(crate) struct ReadAt<B> {
     fd: NornFd,
     buf: B,
     offset: u64,
 }
 
 impl<B> ReadAt<B> {
-    fn new(fd: NornFd, buf: B, offset: u64) -> Self {
+    pub(crate) fn new(fd: NornFd, buf: B, offset: u64) -> Self {
         Self { fd, buf, offset }
     }
 }
 
+impl<B> ReadAt<B>
+where
+    B: StableBufMut,
+{
+    /// Validate the read against the O_DIRECT alignment requirements.
+    fn check_alignment(&mut self, align: Option<DioAlign>) -> io::Result<()> {
+        match align {
+            Some(align) => {
+                let ptr = self.buf.stable_ptr_mut();
+                align.check("read_at", ptr, self.buf.bytes_remaining(), self.offset)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
 impl<B> Operation for ReadAt<B>
 where
     B: StableBufMut,
@@ -224,18 +512,34 @@ where
     }
 }
 
-struct WriteAt<B> {
+pub(crate) struct WriteAt<B> {
     fd: NornFd,
     buf: B,
     offset: u64,
 }
 
 impl<B> WriteAt<B> {
-    fn new(fd: NornFd, buf: B, offset: u64) -> Self {
+    pub(crate) fn new(fd: NornFd, buf: B, offset: u64) -> Self {
         Self { fd, buf, offset }
     }
 }
 
+impl<B> WriteAt<B>
+where
+    B: StableBuf,
+{
+    /// Validate the write against the O_DIRECT alignment requirements.
+    fn check_alignment(&self, align: Option<DioAlign>) -> io::Result<()> {
+        match align {
+            Some(align) => {
+                let ptr = self.buf.stable_ptr();
+                align.check("write_at", ptr, self.buf.bytes_init(), self.offset)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
 impl<B> Operation for WriteAt<B>
 where
     B: StableBuf,
@@ -268,13 +572,288 @@ where
     }
 }
 
-struct Sync {
+struct ReadvAt<V> {
+    fd: NornFd,
+    bufs: V,
+    iovecs: SmallVec<[libc::iovec; 4]>,
+    offset: u64,
+}
+
+impl<V> ReadvAt<V> {
+    fn new(fd: NornFd, bufs: V, offset: u64) -> Self {
+        Self {
+            fd,
+            bufs,
+            iovecs: SmallVec::new(),
+            offset,
+        }
+    }
+}
+
+impl<V> ReadvAt<V>
+where
+    V: StableBufsMut,
+{
+    /// Validate each buffer against the O_DIRECT alignment requirements.
+    fn check_alignment(&mut self, align: Option<DioAlign>) -> io::Result<()> {
+        let Some(align) = align else {
+            return Ok(());
+        };
+        for buf in self.bufs.as_bufs_mut() {
+            let ptr = buf.stable_ptr_mut();
+            align.check("readv_at", ptr, buf.bytes_remaining(), self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl<V> Operation for ReadvAt<V>
+where
+    V: StableBufsMut,
+{
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = self.get_mut();
+        this.iovecs = this
+            .bufs
+            .as_bufs_mut()
+            .iter_mut()
+            .map(|buf| libc::iovec {
+                iov_base: buf.stable_ptr_mut() as _,
+                iov_len: buf.bytes_remaining(),
+            })
+            .collect();
+        let iovecs = this.iovecs.as_ptr();
+        let len = this.iovecs.len() as u32;
+        match this.fd.kind() {
+            FdKind::Fd(fd) => opcode::Readv::new(*fd, iovecs, len),
+            FdKind::Fixed(fd) => opcode::Readv::new(*fd, iovecs, len),
+        }
+        .offset(this.offset)
+        .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl<V> Singleshot for ReadvAt<V>
+where
+    V: StableBufsMut,
+{
+    type Output = (io::Result<usize>, V);
+
+    fn complete(mut self, result: CQEResult) -> Self::Output {
+        match result.result {
+            Ok(n) => {
+                // Distribute the bytes read across the buffers in order.
+                let mut remaining = n as usize;
+                for buf in self.bufs.as_bufs_mut() {
+                    let filled = cmp::min(remaining, buf.bytes_remaining());
+                    unsafe {
+                        buf.set_init(filled);
+                    }
+                    remaining -= filled;
+                }
+                (Ok(n as usize), self.bufs)
+            }
+            Err(err) => (Err(err), self.bufs),
+        }
+    }
+}
+
+struct WritevAt<V> {
+    fd: NornFd,
+    bufs: V,
+    iovecs: SmallVec<[libc::iovec; 4]>,
+    offset: u64,
+}
+
+impl<V> WritevAt<V> {
+    fn new(fd: NornFd, bufs: V, offset: u64) -> Self {
+        Self {
+            fd,
+            bufs,
+            iovecs: SmallVec::new(),
+            offset,
+        }
+    }
+}
+
+impl<V> WritevAt<V>
+where
+    V: StableBufs,
+{
+    /// Validate each buffer against the O_DIRECT alignment requirements.
+    fn check_alignment(&self, align: Option<DioAlign>) -> io::Result<()> {
+        let Some(align) = align else {
+            return Ok(());
+        };
+        for buf in self.bufs.as_bufs() {
+            align.check("writev_at", buf.stable_ptr(), buf.bytes_init(), self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl<V> Operation for WritevAt<V>
+where
+    V: StableBufs,
+{
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = self.get_mut();
+        this.iovecs = this
+            .bufs
+            .as_bufs()
+            .iter()
+            .map(|buf| libc::iovec {
+                iov_base: buf.stable_ptr() as _,
+                iov_len: buf.bytes_init(),
+            })
+            .collect();
+        let iovecs = this.iovecs.as_ptr();
+        let len = this.iovecs.len() as u32;
+        match this.fd.kind() {
+            FdKind::Fd(fd) => opcode::Writev::new(*fd, iovecs, len),
+            FdKind::Fixed(fd) => opcode::Writev::new(*fd, iovecs, len),
+        }
+        .offset(this.offset)
+        .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl<V> Singleshot for WritevAt<V>
+where
+    V: StableBufs,
+{
+    type Output = (io::Result<usize>, V);
+
+    fn complete(self, result: CQEResult) -> Self::Output {
+        match result.result {
+            Ok(n) => (Ok(n as usize), self.bufs),
+            Err(err) => (Err(err), self.bufs),
+        }
+    }
+}
+
+struct ReadFixedAt {
+    fd: NornFd,
+    buf: FixedBuf,
+    offset: u64,
+}
+
+impl ReadFixedAt {
+    fn new(fd: NornFd, buf: FixedBuf, offset: u64) -> Self {
+        Self { fd, buf, offset }
+    }
+
+    /// Validate the read against the O_DIRECT alignment requirements.
+    fn check_alignment(&mut self, align: Option<DioAlign>) -> io::Result<()> {
+        match align {
+            Some(align) => {
+                let ptr = self.buf.stable_ptr_mut();
+                align.check(
+                    "read_fixed_at",
+                    ptr,
+                    self.buf.bytes_remaining(),
+                    self.offset,
+                )
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Operation for ReadFixedAt {
+    fn configure(mut self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let buf = self.buf.stable_ptr_mut();
+        let len = self.buf.bytes_remaining();
+        let index = self.buf.buf_index();
+        match self.fd.kind() {
+            FdKind::Fd(fd) => opcode::ReadFixed::new(*fd, buf, len as _, index),
+            FdKind::Fixed(fd) => opcode::ReadFixed::new(*fd, buf, len as _, index),
+        }
+        .offset(self.offset)
+        .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl Singleshot for ReadFixedAt {
+    type Output = (io::Result<usize>, FixedBuf);
+
+    fn complete(mut self, result: CQEResult) -> Self::Output {
+        match result.result {
+            Ok(n) => {
+                let n = n as usize;
+                unsafe {
+                    self.buf.set_init(n);
+                }
+                (Ok(n), self.buf)
+            }
+            Err(err) => (Err(err), self.buf),
+        }
+    }
+}
+
+struct WriteFixedAt {
+    fd: NornFd,
+    buf: FixedBuf,
+    offset: u64,
+}
+
+impl WriteFixedAt {
+    fn new(fd: NornFd, buf: FixedBuf, offset: u64) -> Self {
+        Self { fd, buf, offset }
+    }
+
+    /// Validate the write against the O_DIRECT alignment requirements.
+    fn check_alignment(&self, align: Option<DioAlign>) -> io::Result<()> {
+        match align {
+            Some(align) => {
+                let ptr = self.buf.stable_ptr();
+                align.check("write_fixed_at", ptr, self.buf.bytes_init(), self.offset)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Operation for WriteFixedAt {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let buf = self.buf.stable_ptr();
+        let len = self.buf.bytes_init();
+        let index = self.buf.buf_index();
+        match self.fd.kind() {
+            FdKind::Fd(fd) => opcode::WriteFixed::new(*fd, buf, len as _, index),
+            FdKind::Fixed(fd) => opcode::WriteFixed::new(*fd, buf, len as _, index),
+        }
+        .offset(self.offset)
+        .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl Singleshot for WriteFixedAt {
+    type Output = (io::Result<usize>, FixedBuf);
+
+    fn complete(self, result: CQEResult) -> Self::Output {
+        match result.result {
+            Ok(n) => (Ok(n as usize), self.buf),
+            Err(err) => (Err(err), self.buf),
+        }
+    }
+}
+
+pub(crate) struct Sync {
     fd: NornFd,
     flags: FsyncFlags,
 }
 
 impl Sync {
-    fn new(fd: NornFd, flags: FsyncFlags) -> Self {
+    pub(crate) fn new(fd: NornFd, flags: FsyncFlags) -> Self {
         Self { fd, flags }
     }
 }
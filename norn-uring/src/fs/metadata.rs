@@ -0,0 +1,338 @@
+use std::ffi::CString;
+use std::fs::Permissions;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::{Duration, SystemTime};
+
+use io_uring::{opcode, types};
+
+use crate::fd::NornFd;
+use crate::fs::dir::{path_to_cstring, DirFd};
+use crate::operation::{CQEResult, Operation, Singleshot};
+use crate::Handle;
+
+/// The fields requested from statx.
+const STATX_MASK: u32 = libc::STATX_BASIC_STATS | libc::STATX_BTIME | libc::STATX_DIOALIGN;
+
+/// Query the metadata for the file at the provided path.
+///
+/// Symlinks are followed.
+pub async fn metadata<P: AsRef<Path>>(path: P) -> io::Result<Metadata> {
+    let statx = Statx::new_path(path.as_ref(), libc::AT_STATX_SYNC_AS_STAT)?;
+    Handle::current().submit(statx).await
+}
+
+/// Query the metadata for the file at the provided path without
+/// following symlinks.
+pub async fn symlink_metadata<P: AsRef<Path>>(path: P) -> io::Result<Metadata> {
+    let flags = libc::AT_STATX_SYNC_AS_STAT | libc::AT_SYMLINK_NOFOLLOW;
+    let statx = Statx::new_path(path.as_ref(), flags)?;
+    Handle::current().submit(statx).await
+}
+
+/// Metadata information about a file.
+///
+/// This is returned by [`metadata`], [`symlink_metadata`] and
+/// [`File::metadata`](crate::fs::File::metadata).
+#[derive(Clone, Copy)]
+pub struct Metadata {
+    statx: libc::statx,
+}
+
+impl std::fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metadata")
+            .field("len", &self.len())
+            .field("mode", &format_args!("{:o}", self.mode()))
+            .field("block_size", &self.block_size())
+            .field("dio_mem_align", &self.dio_mem_align())
+            .field("dio_offset_align", &self.dio_offset_align())
+            .finish()
+    }
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl Metadata {
+    /// Returns the size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.statx.stx_size
+    }
+
+    /// Returns the type of the file.
+    pub fn file_type(&self) -> FileType {
+        FileType::from_mode(self.mode())
+    }
+
+    /// Returns true if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Returns true if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Returns true if this metadata is for a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// Returns the permissions of the file.
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.mode())
+    }
+
+    /// Returns the raw mode bits, including the file type.
+    pub fn mode(&self) -> u32 {
+        self.statx.stx_mode as u32
+    }
+
+    /// Returns the preferred block size for I/O on the file.
+    pub fn block_size(&self) -> u32 {
+        self.statx.stx_blksize
+    }
+
+    /// Returns the number of 512 byte blocks allocated to the file.
+    pub fn blocks(&self) -> u64 {
+        self.statx.stx_blocks
+    }
+
+    /// Returns the inode number of the file.
+    pub fn ino(&self) -> u64 {
+        self.statx.stx_ino
+    }
+
+    /// Returns the number of hard links to the file.
+    pub fn nlink(&self) -> u32 {
+        self.statx.stx_nlink
+    }
+
+    /// Returns the user ID of the owner of the file.
+    pub fn uid(&self) -> u32 {
+        self.statx.stx_uid
+    }
+
+    /// Returns the group ID of the owner of the file.
+    pub fn gid(&self) -> u32 {
+        self.statx.stx_gid
+    }
+
+    /// Returns the required alignment, in bytes, of memory buffers used
+    /// for O_DIRECT I/O on the file.
+    ///
+    /// Returns `None` if the file does not support O_DIRECT, or the kernel
+    /// does not report DIO alignment.
+    pub fn dio_mem_align(&self) -> Option<u32> {
+        self.dio_field(self.statx.stx_dio_mem_align)
+    }
+
+    /// Returns the required alignment, in bytes, of file offsets and
+    /// lengths used for O_DIRECT I/O on the file.
+    ///
+    /// Returns `None` if the file does not support O_DIRECT, or the kernel
+    /// does not report DIO alignment.
+    pub fn dio_offset_align(&self) -> Option<u32> {
+        self.dio_field(self.statx.stx_dio_offset_align)
+    }
+
+    /// Returns the last access time of the file.
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_ATIME, self.statx.stx_atime)
+    }
+
+    /// Returns the last modification time of the file.
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_MTIME, self.statx.stx_mtime)
+    }
+
+    /// Returns the last status change time of the file.
+    pub fn changed(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_CTIME, self.statx.stx_ctime)
+    }
+
+    /// Returns the creation time of the file.
+    ///
+    /// Not all filesystems record the creation time, in which case an error
+    /// of kind [`io::ErrorKind::Unsupported`] is returned.
+    pub fn created(&self) -> io::Result<SystemTime> {
+        self.timestamp(libc::STATX_BTIME, self.statx.stx_btime)
+    }
+
+    fn dio_field(&self, value: u32) -> Option<u32> {
+        if self.statx.stx_mask & libc::STATX_DIOALIGN == 0 || value == 0 {
+            return None;
+        }
+        Some(value)
+    }
+
+    fn timestamp(&self, mask: u32, ts: libc::statx_timestamp) -> io::Result<SystemTime> {
+        if self.statx.stx_mask & mask == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "timestamp not available on this filesystem",
+            ));
+        }
+        Ok(system_time(ts))
+    }
+}
+
+/// Convert a statx timestamp into a [`SystemTime`].
+///
+/// The nanoseconds are always added, so a time before the epoch has negative
+/// seconds and positive nanoseconds.
+fn system_time(ts: libc::statx_timestamp) -> SystemTime {
+    let secs = Duration::from_secs(ts.tv_sec.unsigned_abs());
+    let nanos = Duration::from_nanos(ts.tv_nsec.into());
+    if ts.tv_sec >= 0 {
+        SystemTime::UNIX_EPOCH + secs + nanos
+    } else {
+        SystemTime::UNIX_EPOCH - secs + nanos
+    }
+}
+
+/// The type of a file, such as a regular file, directory or symlink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileType {
+    // The `S_IFMT` bits of the file mode.
+    mode: u32,
+}
+
+impl FileType {
+    pub(crate) fn from_mode(mode: u32) -> Self {
+        Self {
+            mode: mode & libc::S_IFMT,
+        }
+    }
+
+    /// Returns true if this is a regular file.
+    pub fn is_file(&self) -> bool {
+        self.mode == libc::S_IFREG
+    }
+
+    /// Returns true if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.mode == libc::S_IFDIR
+    }
+
+    /// Returns true if this is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.mode == libc::S_IFLNK
+    }
+
+    /// Returns true if this is a block device.
+    pub fn is_block_device(&self) -> bool {
+        self.mode == libc::S_IFBLK
+    }
+
+    /// Returns true if this is a character device.
+    pub fn is_char_device(&self) -> bool {
+        self.mode == libc::S_IFCHR
+    }
+
+    /// Returns true if this is a FIFO.
+    pub fn is_fifo(&self) -> bool {
+        self.mode == libc::S_IFIFO
+    }
+
+    /// Returns true if this is a socket.
+    pub fn is_socket(&self) -> bool {
+        self.mode == libc::S_IFSOCK
+    }
+}
+
+pub(crate) struct Statx {
+    fd: DirFd,
+    path: CString,
+    flags: i32,
+    statx: MaybeUninit<libc::statx>,
+}
+
+impl Statx {
+    fn new_path(path: &Path, flags: i32) -> io::Result<Self> {
+        Self::new_at(None, path, flags)
+    }
+
+    /// Query the metadata of a path, resolved relative to `dir`.
+    pub(crate) fn new_at(dir: Option<NornFd>, path: &Path, flags: i32) -> io::Result<Self> {
+        let fd = DirFd::new(dir)?;
+        let path = path_to_cstring(path)?;
+        Ok(Self {
+            fd,
+            path,
+            flags,
+            statx: MaybeUninit::zeroed(),
+        })
+    }
+
+    /// Query the metadata of an open file descriptor.
+    pub(crate) fn new_fd(fd: NornFd) -> io::Result<Self> {
+        Ok(Self {
+            fd: DirFd::new(Some(fd))?,
+            path: CString::default(),
+            flags: libc::AT_STATX_SYNC_AS_STAT | libc::AT_EMPTY_PATH,
+            statx: MaybeUninit::zeroed(),
+        })
+    }
+}
+
+impl Operation for Statx {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = self.get_mut();
+        let dirfd = this.fd.fd();
+        let statx = this.statx.as_mut_ptr() as *mut types::statx;
+        opcode::Statx::new(dirfd, this.path.as_ptr(), statx)
+            .flags(this.flags)
+            .mask(STATX_MASK)
+            .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl Singleshot for Statx {
+    type Output = io::Result<Metadata>;
+
+    fn complete(self, result: CQEResult) -> Self::Output {
+        result.result?;
+        // Safety: The kernel has filled in the statx buffer, and it was zeroed
+        // prior to submission.
+        let statx = unsafe { self.statx.assume_init() };
+        Ok(Metadata { statx })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(tv_sec: i64, tv_nsec: u32) -> libc::statx_timestamp {
+        // Safety: statx_timestamp is plain old data.
+        let mut ts: libc::statx_timestamp = unsafe { std::mem::zeroed() };
+        ts.tv_sec = tv_sec;
+        ts.tv_nsec = tv_nsec;
+        ts
+    }
+
+    #[test]
+    fn system_time_from_timestamp() {
+        let epoch = SystemTime::UNIX_EPOCH;
+        assert_eq!(epoch, system_time(timestamp(0, 0)));
+        assert_eq!(
+            epoch + Duration::new(1, 250),
+            system_time(timestamp(1, 250))
+        );
+        // Half a second before the epoch is -1 seconds plus 500ms.
+        assert_eq!(
+            epoch - Duration::from_millis(500),
+            system_time(timestamp(-1, 500_000_000))
+        );
+        assert_eq!(
+            epoch - Duration::from_secs(2),
+            system_time(timestamp(-2, 0))
+        );
+    }
+}
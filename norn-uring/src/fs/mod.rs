@@ -1,9 +1,18 @@
 //! Filesystem operations.
 
 mod dir;
-mod file;
+pub(crate) mod file;
+mod metadata;
 mod opts;
+mod read_dir;
+mod stream;
 
-pub use dir::{create_dir, remove_dir, remove_file};
+pub use dir::{
+    create_dir, create_dir_all, hard_link, read_dir, remove_dir, remove_dir_all, remove_file,
+    rename, rename_exchange, rename_noreplace, symlink, Dir,
+};
 pub use file::File;
+pub use metadata::{metadata, symlink_metadata, FileType, Metadata};
 pub use opts::OpenOptions;
+pub use read_dir::{DirEntry, ReadDir};
+pub use stream::FileStream;
@@ -26,6 +26,8 @@ pub struct OpenOptions {
     pub(crate) direct: bool,
     pub(crate) sync: bool,
     pub(crate) dsync: bool,
+    pub(crate) resolve_beneath: bool,
+    pub(crate) resolve_no_symlinks: bool,
 }
 
 impl OpenOptions {
@@ -133,6 +135,28 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the option to reject paths which escape the starting directory.
+    ///
+    /// When set, the file is opened with `openat2` and `RESOLVE_BENEATH`.
+    /// Absolute paths, `..` components and symlinks which resolve outside
+    /// of the starting directory cause the open to fail with `EXDEV`. This
+    /// is most useful with [`Dir::open_file`](crate::fs::Dir::open_file) for
+    /// safely opening untrusted paths inside a root directory.
+    pub fn resolve_beneath(&mut self, resolve_beneath: bool) -> &mut Self {
+        self.resolve_beneath = resolve_beneath;
+        self
+    }
+
+    /// Sets the option to reject paths containing symlinks.
+    ///
+    /// When set, the file is opened with `openat2` and `RESOLVE_NO_SYMLINKS`.
+    /// If any component of the path is a symlink the open fails with
+    /// `ELOOP`.
+    pub fn resolve_no_symlinks(&mut self, resolve_no_symlinks: bool) -> &mut Self {
+        self.resolve_no_symlinks = resolve_no_symlinks;
+        self
+    }
+
     /// Open the file with the configured options.
     pub async fn open<P: AsRef<Path>>(self, path: P) -> io::Result<File> {
         File::open_with_options(path, self).await
@@ -160,6 +184,17 @@ impl OpenOptions {
         Ok(access_mode)
     }
 
+    pub(crate) fn get_resolve_flags(&self) -> u64 {
+        let mut resolve = 0;
+        if self.resolve_beneath {
+            resolve |= libc::RESOLVE_BENEATH;
+        }
+        if self.resolve_no_symlinks {
+            resolve |= libc::RESOLVE_NO_SYMLINKS;
+        }
+        resolve
+    }
+
     pub(crate) fn get_creation_mode(&self) -> io::Result<i32> {
         match (self.write, self.append) {
             (true, false) => {}
@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::ffi::{CStr, OsStr, OsString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::fd::{FdKind, NornFd};
+use crate::fs::metadata::{FileType, Metadata, Statx};
+use crate::Handle;
+
+/// The size of the buffer passed to `getdents64`.
+const DIRENT_BUF_LEN: usize = 8192;
+
+/// A stream over the entries in a directory.
+///
+/// This is returned by [`read_dir`](crate::fs::read_dir) and
+/// [`Dir::read_dir`](crate::fs::Dir::read_dir).
+///
+/// Entries are read in batches with a blocking call, see
+/// [`read_dir`](crate::fs::read_dir). The stream yields to the executor
+/// between batches.
+pub struct ReadDir {
+    fd: NornFd,
+    entries: VecDeque<DirEntry>,
+    done: bool,
+    // Set after reading a batch, so that the stream yields before the next one.
+    yield_next: bool,
+}
+
+impl std::fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadDir").field("done", &self.done).finish()
+    }
+}
+
+impl ReadDir {
+    pub(crate) fn new(fd: NornFd) -> Self {
+        Self {
+            fd,
+            entries: VecDeque::new(),
+            done: false,
+            yield_next: false,
+        }
+    }
+
+    /// Read the next batch of entries into `self.entries`.
+    fn fill(&mut self) -> io::Result<()> {
+        let raw = match self.fd.kind() {
+            FdKind::Fd(fd) => fd.0,
+            FdKind::Fixed(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "read_dir does not support fixed files",
+                ))
+            }
+        };
+        let mut buf = vec![0u8; DIRENT_BUF_LEN];
+        // Safety: The buffer is valid for writes of `buf.len()` bytes.
+        let n = unsafe {
+            libc::syscall(
+                libc::SYS_getdents64,
+                raw,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if n == 0 {
+            self.done = true;
+            return Ok(());
+        }
+        self.yield_next = true;
+        let mut offset = 0;
+        while offset < n as usize {
+            // Safety: The kernel wrote a sequence of complete `linux_dirent64` records
+            // into the first `n` bytes of the buffer. The records are not necessarily
+            // aligned within the buffer, so fields are read unaligned.
+            let (ino, reclen, d_type, name) = unsafe {
+                let dirent = buf.as_ptr().add(offset) as *const libc::dirent64;
+                let ino = std::ptr::addr_of!((*dirent).d_ino).read_unaligned();
+                let reclen = std::ptr::addr_of!((*dirent).d_reclen).read_unaligned();
+                let d_type = std::ptr::addr_of!((*dirent).d_type).read_unaligned();
+                let name = CStr::from_ptr(std::ptr::addr_of!((*dirent).d_name) as *const _);
+                (ino, reclen, d_type, name)
+            };
+            offset += reclen as usize;
+            let name = name.to_bytes();
+            if name == b"." || name == b".." {
+                continue;
+            }
+            self.entries.push_back(DirEntry {
+                dir: self.fd.clone(),
+                name: OsStr::from_bytes(name).to_os_string(),
+                ino,
+                d_type,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Stream for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.entries.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            // Let other tasks run between the blocking calls.
+            if this.yield_next {
+                this.yield_next = false;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if let Err(err) = this.fill() {
+                this.done = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+    }
+}
+
+/// An entry in a directory, yielded by [`ReadDir`].
+pub struct DirEntry {
+    dir: NornFd,
+    name: OsString,
+    ino: u64,
+    d_type: u8,
+}
+
+impl std::fmt::Debug for DirEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DirEntry")
+            .field("name", &self.name)
+            .field("ino", &self.ino)
+            .finish()
+    }
+}
+
+impl DirEntry {
+    /// Returns the name of the entry, without any leading path components.
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the inode number of the entry.
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// Returns the type of the entry.
+    ///
+    /// Symlinks are not followed. The type reported by the directory listing
+    /// is used if available, otherwise the entry is queried with statx.
+    pub async fn file_type(&self) -> io::Result<FileType> {
+        let mode = match self.d_type {
+            libc::DT_REG => libc::S_IFREG,
+            libc::DT_DIR => libc::S_IFDIR,
+            libc::DT_LNK => libc::S_IFLNK,
+            libc::DT_BLK => libc::S_IFBLK,
+            libc::DT_CHR => libc::S_IFCHR,
+            libc::DT_FIFO => libc::S_IFIFO,
+            libc::DT_SOCK => libc::S_IFSOCK,
+            _ => return Ok(self.metadata().await?.file_type()),
+        };
+        Ok(FileType::from_mode(mode))
+    }
+
+    /// Query the metadata of the entry.
+    ///
+    /// Symlinks are not followed.
+    pub async fn metadata(&self) -> io::Result<Metadata> {
+        let flags = libc::AT_STATX_SYNC_AS_STAT | libc::AT_SYMLINK_NOFOLLOW;
+        let statx = Statx::new_at(Some(self.dir.clone()), Path::new(&self.name), flags)?;
+        Handle::current().submit(statx).await
+    }
+}
@@ -0,0 +1,420 @@
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::{cmp, mem};
+
+use crate::fs::file::{ReadAt, WriteAt};
+use crate::fs::metadata::Statx;
+use crate::fs::File;
+use crate::operation::Op;
+
+/// The default capacity of the [`FileStream`] buffer.
+const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+pin_project_lite::pin_project! {
+    /// [`FileStream`] is a buffered, seekable stream over a [`File`].
+    ///
+    /// The stream tracks a cursor into the file and issues positional reads
+    /// and writes against it using an owned buffer. It implements
+    /// [`AsyncRead`](tokio::io::AsyncRead), [`AsyncWrite`](tokio::io::AsyncWrite),
+    /// [`AsyncSeek`](tokio::io::AsyncSeek) and [`AsyncBufRead`](tokio::io::AsyncBufRead),
+    /// along with the `futures::io` equivalents.
+    ///
+    /// Writes are buffered until the buffer fills, or until the stream is
+    /// flushed, seeked or read from. Buffered writes are not flushed on drop,
+    /// so the stream should be flushed or shut down before it is dropped.
+    /// Flushing only writes the buffer to the file, use
+    /// [`File::sync`] via [`FileStream::get_ref`] for durability.
+    pub struct FileStream {
+        file: File,
+        // `buf` holds either read-ahead data, or pending writes if `writing` is
+        // set. It is moved into the in-flight operation while one is running.
+        buf: Vec<u8>,
+        cap: usize,
+        // When reading, `start` is the index of the next unread byte in `buf`.
+        start: usize,
+        writing: bool,
+        // `pos` is the logical position of the cursor. Pending writes in `buf`
+        // end at `pos`, and unread data in `buf` starts at `pos`.
+        pos: u64,
+        seek: Option<SeekFrom>,
+        #[pin]
+        state: State,
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[project = StateProj]
+    enum State {
+        Idle,
+        Reading { #[pin] op: Op<ReadAt<Vec<u8>>> },
+        Writing { #[pin] op: Op<WriteAt<Vec<u8>>> },
+        Seeking { #[pin] op: Op<Statx> },
+    }
+}
+
+impl std::fmt::Debug for FileStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileStream")
+            .field("pos", &self.pos)
+            .field("cap", &self.cap)
+            .finish()
+    }
+}
+
+impl FileStream {
+    /// Create a new [`FileStream`] with the default buffer capacity.
+    pub fn new(file: File) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, file)
+    }
+
+    /// Create a new [`FileStream`] with the provided buffer capacity.
+    ///
+    /// ### Panics
+    /// Panics if `cap` is zero.
+    pub fn with_capacity(cap: usize, file: File) -> Self {
+        assert!(cap > 0, "FileStream capacity must be non-zero");
+        Self {
+            file,
+            buf: Vec::with_capacity(cap),
+            cap,
+            start: 0,
+            writing: false,
+            pos: 0,
+            seek: None,
+            state: State::Idle,
+        }
+    }
+
+    /// Returns a reference to the underlying file.
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns the current position of the cursor.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Consume the stream, returning the underlying file.
+    ///
+    /// Any buffered writes which have not been flushed are discarded.
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+
+    /// Write out any buffered writes, waiting for an in-flight write to
+    /// complete.
+    fn poll_flush_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::Writing { op } => {
+                    let (res, mut buf) = ready!(op.poll(cx));
+                    this.state.set(State::Idle);
+                    let res = res.and_then(|n| match n {
+                        0 => Err(io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            "failed to write buffered data",
+                        )),
+                        n => Ok(n),
+                    });
+                    match res {
+                        Ok(n) => {
+                            buf.drain(..n);
+                            *this.buf = buf;
+                        }
+                        Err(err) => {
+                            *this.buf = buf;
+                            return Poll::Ready(Err(err));
+                        }
+                    }
+                }
+                _ if !*this.writing => return Poll::Ready(Ok(())),
+                StateProj::Idle if this.buf.is_empty() => {
+                    *this.writing = false;
+                    return Poll::Ready(Ok(()));
+                }
+                StateProj::Idle => {
+                    let buf = mem::take(this.buf);
+                    let offset = *this.pos - buf.len() as u64;
+                    let write = WriteAt::new(this.file.fd().clone(), buf, offset);
+                    let op = this.file.handle().submit(write);
+                    this.state.set(State::Writing { op });
+                }
+                StateProj::Reading { .. } | StateProj::Seeking { .. } => {
+                    unreachable!("reads and seeks are never in flight with buffered writes")
+                }
+            }
+        }
+    }
+
+    /// Drop any in-flight read or seek, and any read-ahead data.
+    fn discard_read(self: Pin<&mut Self>) {
+        let mut this = self.project();
+        if matches!(
+            this.state.as_mut().project(),
+            StateProj::Reading { .. } | StateProj::Seeking { .. }
+        ) {
+            // Dropping the operation cancels it.
+            this.state.set(State::Idle);
+        }
+        if !*this.writing {
+            this.buf.clear();
+            *this.start = 0;
+        }
+    }
+
+    fn poll_fill_buf_inner(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&[u8]>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::Idle => {
+                    if *this.start < this.buf.len() {
+                        break;
+                    }
+                    let mut buf = mem::take(this.buf);
+                    buf.clear();
+                    buf.reserve(*this.cap);
+                    *this.start = 0;
+                    let read = ReadAt::new(this.file.fd().clone(), buf, *this.pos);
+                    let op = this.file.handle().submit(read);
+                    this.state.set(State::Reading { op });
+                }
+                StateProj::Reading { op } => {
+                    let (res, buf) = ready!(op.poll(cx));
+                    this.state.set(State::Idle);
+                    *this.buf = buf;
+                    *this.start = 0;
+                    res?;
+                    break;
+                }
+                StateProj::Seeking { .. } => {
+                    // An unfinished seek was abandoned.
+                    *this.seek = None;
+                    this.state.set(State::Idle);
+                }
+                StateProj::Writing { .. } => unreachable!("writes are flushed before reading"),
+            }
+        }
+        Poll::Ready(Ok(&this.buf[*this.start..]))
+    }
+
+    fn consume_inner(self: Pin<&mut Self>, amt: usize) {
+        let this = self.project();
+        let amt = cmp::min(amt, this.buf.len() - *this.start);
+        *this.start += amt;
+        *this.pos += amt as u64;
+    }
+
+    fn poll_write_inner(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if !self.writing {
+            // Read-ahead data is discarded, the cursor is unaffected.
+            self.as_mut().discard_read();
+            *self.as_mut().project().seek = None;
+        }
+        let full = self.buf.len() >= self.cap;
+        if full || matches!(self.state, State::Writing { .. }) {
+            ready!(self.as_mut().poll_flush_buf(cx))?;
+        }
+        let this = self.project();
+        let n = cmp::min(*this.cap - this.buf.len(), data.len());
+        this.buf.extend_from_slice(&data[..n]);
+        *this.pos += n as u64;
+        *this.writing = true;
+        Poll::Ready(Ok(n))
+    }
+
+    fn start_seek_inner(mut self: Pin<&mut Self>, pos: SeekFrom) {
+        let mut this = self.as_mut().project();
+        if let StateProj::Seeking { .. } = this.state.as_mut().project() {
+            this.state.set(State::Idle);
+        }
+        *this.seek = Some(pos);
+    }
+
+    fn poll_complete_inner(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        let Some(target) = self.seek else {
+            return Poll::Ready(Ok(self.pos));
+        };
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        let (base, offset) = match target {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::Current(n) => (self.pos, n),
+            SeekFrom::End(n) => {
+                let mut this = self.as_mut().project();
+                let len = loop {
+                    match this.state.as_mut().project() {
+                        StateProj::Seeking { op } => {
+                            let res = ready!(op.poll(cx));
+                            this.state.set(State::Idle);
+                            match res {
+                                Ok(metadata) => break metadata.len(),
+                                Err(err) => {
+                                    *this.seek = None;
+                                    return Poll::Ready(Err(err));
+                                }
+                            }
+                        }
+                        _ => {
+                            let statx = match Statx::new_fd(this.file.fd().clone()) {
+                                Ok(statx) => statx,
+                                Err(err) => {
+                                    *this.seek = None;
+                                    return Poll::Ready(Err(err));
+                                }
+                            };
+                            let op = this.file.handle().submit(statx);
+                            // This drops any in-flight read.
+                            this.state.set(State::Seeking { op });
+                        }
+                    }
+                };
+                (len, n)
+            }
+        };
+        *self.as_mut().project().seek = None;
+        let Some(new) = base.checked_add_signed(offset) else {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )));
+        };
+        let this = self.as_mut().project();
+        // Keep the read-ahead data if the new position is within it.
+        let buf_start = *this.pos - *this.start as u64;
+        let buf_end = buf_start + this.buf.len() as u64;
+        if matches!(*this.state, State::Idle) && (buf_start..=buf_end).contains(&new) {
+            *this.start = (new - buf_start) as usize;
+        } else if new != *this.pos {
+            self.as_mut().discard_read();
+        }
+        *self.as_mut().project().pos = new;
+        Poll::Ready(Ok(new))
+    }
+}
+
+impl tokio::io::AsyncRead for FileStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let rem = ready!(self.as_mut().poll_fill_buf_inner(cx))?;
+        let n = cmp::min(rem.len(), buf.remaining());
+        buf.put_slice(&rem[..n]);
+        self.consume_inner(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl tokio::io::AsyncBufRead for FileStream {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.poll_fill_buf_inner(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.consume_inner(amt)
+    }
+}
+
+impl tokio::io::AsyncWrite for FileStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_inner(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_buf(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_buf(cx)
+    }
+}
+
+impl tokio::io::AsyncSeek for FileStream {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        self.start_seek_inner(position);
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        self.poll_complete_inner(cx)
+    }
+}
+
+impl futures_io::AsyncRead for FileStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let rem = ready!(self.as_mut().poll_fill_buf_inner(cx))?;
+        let n = cmp::min(rem.len(), buf.len());
+        buf[..n].copy_from_slice(&rem[..n]);
+        self.consume_inner(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl futures_io::AsyncBufRead for FileStream {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.poll_fill_buf_inner(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.consume_inner(amt)
+    }
+}
+
+impl futures_io::AsyncWrite for FileStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_inner(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_buf(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_buf(cx)
+    }
+}
+
+impl futures_io::AsyncSeek for FileStream {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        // `poll_seek` is called repeatedly with the same position until it
+        // completes, so only start the seek on the first call.
+        if self.seek.is_none() {
+            self.as_mut().start_seek_inner(pos);
+        }
+        self.poll_complete_inner(cx)
+    }
+}
@@ -14,12 +14,15 @@ pub(crate) mod driver;
 pub(crate) mod error;
 pub(crate) mod fd;
 pub(crate) mod operation;
+pub(crate) mod time;
 pub(crate) mod util;
 
 pub mod buf;
 pub mod bufring;
+pub mod fixedbuf;
 pub mod fs;
 pub mod net;
+pub mod pipe;
 
 pub use driver::{Driver, Handle};
 pub use util::noop;
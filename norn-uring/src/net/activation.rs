@@ -0,0 +1,379 @@
+//! Socket activation.
+//!
+//! [`ListenFds`] adopts sockets passed to the process using the systemd socket
+//! activation protocol: the sockets are open file descriptors starting at 3,
+//! `LISTEN_FDS` holds their count, `LISTEN_FDNAMES` their colon separated names
+//! and `LISTEN_PID` the pid of the process they are meant for.
+//!
+//! [`Handoff`] uses the same protocol to pass listeners to the program which
+//! replaces the current process, so that connections queue in the listen
+//! backlog rather than being refused while it restarts.
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use socket2::{Domain, Type};
+
+use crate::net::{TcpListener, UdpSocket};
+
+const LOG: &str = "norn_uring::net::activation";
+
+/// The first file descriptor passed by socket activation.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The name of file descriptors passed without `LISTEN_FDNAMES`.
+const UNKNOWN_NAME: &str = "unknown";
+
+/// Set once the passed file descriptors have been adopted, so that they are
+/// not owned twice.
+static ADOPTED: AtomicBool = AtomicBool::new(false);
+
+/// The sockets passed to this process by socket activation.
+#[derive(Debug, Default)]
+pub struct ListenFds {
+    fds: Vec<ListenFd>,
+}
+
+impl ListenFds {
+    /// Takes ownership of the sockets passed to this process.
+    ///
+    /// This returns no sockets if `LISTEN_PID` is not set or is meant for another
+    /// process. The sockets are adopted at most once per process, later calls
+    /// return no sockets. The sockets are marked close-on-exec.
+    ///
+    /// The activation variables are left in the environment, see
+    /// [`ListenFds::clear_env`].
+    pub fn from_env() -> io::Result<ListenFds> {
+        if std::env::var_os("LISTEN_PID").is_none() || ADOPTED.swap(true, Ordering::SeqCst) {
+            return Ok(ListenFds::default());
+        }
+        let res = Self::parse_env();
+        if res.is_err() {
+            // No file descriptor was adopted, so a later call may try again.
+            ADOPTED.store(false, Ordering::SeqCst);
+        }
+        res
+    }
+
+    /// Removes the activation variables from the environment.
+    ///
+    /// Child processes do not check `LISTEN_PID` against their own pid if they
+    /// replace this process with exec, and would otherwise try to adopt file
+    /// descriptors which are no longer open.
+    ///
+    /// # Safety
+    ///
+    /// No other thread may read or write the environment at the same time, see
+    /// [`std::env::remove_var`]. Call this early in `main`, before any threads
+    /// are spawned.
+    pub unsafe fn clear_env() {
+        for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+            std::env::remove_var(name);
+        }
+    }
+
+    fn parse_env() -> io::Result<ListenFds> {
+        let Some(pid) = std::env::var_os("LISTEN_PID") else {
+            return Ok(ListenFds::default());
+        };
+        let pid: u32 = parse_var("LISTEN_PID", pid)?;
+        if pid != std::process::id() {
+            log::debug!(target: LOG, "listen_fds.other_pid {pid}");
+            return Ok(ListenFds::default());
+        }
+        let count = parse_count(
+            std::env::var_os("LISTEN_FDS").unwrap_or_default(),
+            fd_limit()?,
+        )?;
+        let names: Vec<String> = match std::env::var("LISTEN_FDNAMES") {
+            Ok(names) if names.is_empty() => Vec::new(),
+            Ok(names) => names.split(':').map(String::from).collect(),
+            Err(_) => (0..count).map(|_| UNKNOWN_NAME.to_string()).collect(),
+        };
+        if names.len() != count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "LISTEN_FDNAMES does not match LISTEN_FDS",
+            ));
+        }
+        // Check that every file descriptor is open before taking ownership of any,
+        // so that none are closed if one of them is missing.
+        for fd in (LISTEN_FDS_START..).take(count) {
+            // Safety: the activation protocol passes ownership of the file descriptors.
+            if unsafe { libc::fcntl(fd, libc::F_GETFD) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let mut fds = Vec::with_capacity(names.len());
+        for (fd, name) in (LISTEN_FDS_START..).zip(names) {
+            // Safety: the file descriptor is open and not owned elsewhere.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            fds.push(ListenFd { fd, name });
+        }
+        log::debug!(target: LOG, "listen_fds.adopted {}", fds.len());
+        Ok(ListenFds { fds })
+    }
+
+    /// Returns the number of sockets not taken yet.
+    pub fn len(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// Returns true if all sockets have been taken.
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+
+    /// Takes the first socket with the given name.
+    pub fn take(&mut self, name: &str) -> Option<ListenFd> {
+        let index = self.fds.iter().position(|fd| fd.name == name)?;
+        Some(self.fds.remove(index))
+    }
+
+    /// Takes the first socket with the given name as a [`TcpListener`].
+    pub fn take_tcp_listener(&mut self, name: &str) -> io::Result<TcpListener> {
+        self.take(name)
+            .ok_or_else(|| not_found(name))?
+            .into_tcp_listener()
+    }
+
+    /// Takes the first socket with the given name as a [`UdpSocket`].
+    pub fn take_udp_socket(&mut self, name: &str) -> io::Result<UdpSocket> {
+        self.take(name)
+            .ok_or_else(|| not_found(name))?
+            .into_udp_socket()
+    }
+}
+
+impl IntoIterator for ListenFds {
+    type Item = ListenFd;
+    type IntoIter = std::vec::IntoIter<ListenFd>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.fds.into_iter()
+    }
+}
+
+/// A socket passed to this process by socket activation.
+#[derive(Debug)]
+pub struct ListenFd {
+    fd: OwnedFd,
+    name: String,
+}
+
+impl ListenFd {
+    /// Returns the name of the socket, `"unknown"` if no names were passed.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Converts the socket into a [`TcpListener`].
+    ///
+    /// This fails if the socket is not a listening IPv4 or IPv6 stream socket.
+    pub fn into_tcp_listener(self) -> io::Result<TcpListener> {
+        let (socket, name) = self.validate(Type::STREAM)?;
+        if !socket.is_listener()? {
+            return Err(invalid_socket(&name, "is not listening"));
+        }
+        TcpListener::from_std(socket.into())
+    }
+
+    /// Converts the socket into a [`UdpSocket`].
+    ///
+    /// This fails if the socket is not an IPv4 or IPv6 datagram socket.
+    pub fn into_udp_socket(self) -> io::Result<UdpSocket> {
+        let (socket, _) = self.validate(Type::DGRAM)?;
+        UdpSocket::from_std(socket.into())
+    }
+
+    fn validate(self, socket_type: Type) -> io::Result<(socket2::Socket, String)> {
+        let socket = socket2::Socket::from(self.fd);
+        let domain = socket.domain()?;
+        if domain != Domain::IPV4 && domain != Domain::IPV6 {
+            return Err(invalid_socket(&self.name, "is not an IP socket"));
+        }
+        if socket.r#type()? != socket_type {
+            return Err(invalid_socket(&self.name, "has the wrong socket type"));
+        }
+        Ok((socket, self.name))
+    }
+}
+
+impl AsRawFd for ListenFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl AsFd for ListenFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl From<ListenFd> for OwnedFd {
+    fn from(fd: ListenFd) -> OwnedFd {
+        fd.fd
+    }
+}
+
+/// Passes sockets to the program which replaces the current process.
+///
+/// The sockets are passed using the socket activation protocol, so the new
+/// program adopts them with [`ListenFds::from_env`].
+#[derive(Debug, Default)]
+pub struct Handoff {
+    fds: Vec<(String, OwnedFd)>,
+}
+
+impl Handoff {
+    /// Creates a handoff with no sockets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a socket to pass under the given name.
+    ///
+    /// The socket is duplicated, so it remains usable by this process until
+    /// [`Handoff::exec`] is called. Names must not be empty or contain `:`.
+    pub fn add(&mut self, name: impl Into<String>, fd: BorrowedFd<'_>) -> io::Result<()> {
+        let name = name.into();
+        if name.is_empty() || name.contains(':') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "socket names must not be empty or contain ':'",
+            ));
+        }
+        self.fds.push((name, fd.try_clone_to_owned()?));
+        Ok(())
+    }
+
+    /// Replaces the current process with `command`, passing it the sockets.
+    ///
+    /// The process keeps its pid, so a service manager keeps tracking it. This
+    /// only returns if the exec failed, in which case file descriptors starting
+    /// at 3 may have been replaced by the sockets and the process should exit.
+    pub fn exec(self, command: &mut Command) -> io::Error {
+        let count = self.fds.len() as RawFd;
+        // Move the sockets above the range they are passed in, so placing one
+        // does not overwrite another which is yet to be placed.
+        let mut fds = Vec::with_capacity(self.fds.len());
+        let mut names = Vec::with_capacity(self.fds.len());
+        for (name, fd) in self.fds {
+            // Safety: `fd` is open, the duplicate is owned below.
+            let dup = unsafe {
+                libc::fcntl(
+                    fd.as_raw_fd(),
+                    libc::F_DUPFD_CLOEXEC,
+                    LISTEN_FDS_START + count,
+                )
+            };
+            if dup < 0 {
+                return io::Error::last_os_error();
+            }
+            // Safety: `dup` was just opened.
+            fds.push(unsafe { OwnedFd::from_raw_fd(dup) });
+            names.push(name);
+        }
+        let raw: Vec<RawFd> = fds.iter().map(|fd| fd.as_raw_fd()).collect();
+        command
+            .env("LISTEN_PID", std::process::id().to_string())
+            .env("LISTEN_FDS", count.to_string())
+            .env("LISTEN_FDNAMES", names.join(":"));
+        // Safety: `dup2` is async-signal-safe. It also clears close-on-exec on
+        // the placed file descriptors.
+        unsafe {
+            command.pre_exec(move || {
+                for (target, fd) in (LISTEN_FDS_START..).zip(&raw) {
+                    if libc::dup2(*fd, target) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+        log::debug!(target: LOG, "handoff.exec {}", names.len());
+        let err = command.exec();
+        drop(fds);
+        err
+    }
+}
+
+fn parse_var<T: std::str::FromStr>(name: &str, value: std::ffi::OsString) -> io::Result<T> {
+    value.to_str().and_then(|v| v.parse().ok()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid value for {name}: {value:?}"),
+        )
+    })
+}
+
+/// Parse `LISTEN_FDS`, which must leave the file descriptors it covers within
+/// the range of [`RawFd`].
+/// Returns the soft limit on the number of open file descriptors.
+fn fd_limit() -> io::Result<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // Safety: `limit` is valid for writes.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(limit.rlim_cur)
+}
+
+/// Parses `LISTEN_FDS`. The passed file descriptors must be below `fd_limit`.
+fn parse_count(value: std::ffi::OsString, fd_limit: u64) -> io::Result<usize> {
+    let count: usize = parse_var("LISTEN_FDS", value)?;
+    let max = fd_limit
+        .min(RawFd::MAX as u64)
+        .saturating_sub(LISTEN_FDS_START as u64);
+    if count as u64 > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("too many file descriptors in LISTEN_FDS: {count}"),
+        ));
+    }
+    Ok(count)
+}
+
+fn not_found(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no socket named {name:?} was passed"),
+    )
+}
+
+fn invalid_socket(name: &str, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("socket {name:?} {reason}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_fds_count() {
+        assert_eq!(2, parse_count("2".into(), 1024).unwrap());
+        for value in ["-1", "x", "", "1022", "2147483645"] {
+            let err = parse_count(value.into(), 1024).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind(), "{value}");
+        }
+        assert_eq!(1021, parse_count("1021".into(), 1024).unwrap());
+        let unlimited = libc::RLIM_INFINITY;
+        assert_eq!(
+            2147483644,
+            parse_count("2147483644".into(), unlimited).unwrap()
+        );
+        assert!(parse_count("2147483645".into(), unlimited).is_err());
+    }
+}
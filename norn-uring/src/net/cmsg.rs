@@ -0,0 +1,306 @@
+//! Socket control messages.
+//!
+//! Control messages carry ancillary data alongside a datagram, such as the
+//! local address a packet was received on or the time it was received.
+//! Messages to send are encoded into a [`ControlMessageBuf`].
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, SystemTime};
+
+/// A control message sent or received alongside a datagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ControlMessage<'a> {
+    /// `IP_PKTINFO` or `IPV6_PKTINFO`.
+    ///
+    /// When received, this holds the destination address of the packet and
+    /// the interface it arrived on. When sent, this selects the source address
+    /// and outgoing interface of the packet. An `ifindex` of zero lets the
+    /// kernel choose the interface.
+    PacketInfo {
+        /// The destination address of a received packet, or the source address
+        /// of a sent packet.
+        addr: IpAddr,
+        /// The index of the interface.
+        ifindex: u32,
+    },
+    /// The type of service field of the packet, from `IP_TOS`.
+    Tos(u8),
+    /// The size of each segment when sending a buffer as multiple datagrams
+    /// with UDP GSO, from `UDP_SEGMENT`.
+    SegmentSize(u16),
+    /// The time the packet was received, from `SO_TIMESTAMPNS`.
+    Timestamp(SystemTime),
+    /// The size of the segments coalesced into a single datagram by UDP GRO.
+    GroSegmentSize(u16),
+    /// A control message which is not otherwise recognized.
+    Other {
+        /// The originating protocol, `cmsg_level`.
+        level: i32,
+        /// The protocol specific type, `cmsg_type`.
+        ty: i32,
+        /// The message payload.
+        data: &'a [u8],
+    },
+}
+
+/// An iterator over the control messages in a control buffer.
+#[derive(Debug, Clone)]
+pub struct ControlMessages<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ControlMessages<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for ControlMessages<'a> {
+    type Item = ControlMessage<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        const HDR_LEN: usize = std::mem::size_of::<libc::cmsghdr>();
+        let remaining = &self.buf[self.offset.min(self.buf.len())..];
+        if remaining.len() < HDR_LEN {
+            return None;
+        }
+        // Safety: There are at least `HDR_LEN` bytes remaining. The buffer is
+        // not necessarily aligned, so the header is read unaligned.
+        let hdr = unsafe { remaining.as_ptr().cast::<libc::cmsghdr>().read_unaligned() };
+        let len = hdr.cmsg_len;
+        if len < cmsg_len(0) || len > remaining.len() {
+            return None;
+        }
+        let data = &remaining[cmsg_len(0)..len];
+        self.offset += cmsg_space(len - cmsg_len(0));
+        Some(parse(hdr.cmsg_level, hdr.cmsg_type, data))
+    }
+}
+
+fn parse(level: i32, ty: i32, data: &[u8]) -> ControlMessage<'_> {
+    match (level, ty) {
+        (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+            if let Some(info) = read::<libc::in_pktinfo>(data) {
+                return ControlMessage::PacketInfo {
+                    addr: IpAddr::V4(Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr))),
+                    ifindex: info.ipi_ifindex as u32,
+                };
+            }
+        }
+        (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+            if let Some(info) = read::<libc::in6_pktinfo>(data) {
+                return ControlMessage::PacketInfo {
+                    addr: IpAddr::V6(Ipv6Addr::from(info.ipi6_addr.s6_addr)),
+                    ifindex: info.ipi6_ifindex,
+                };
+            }
+        }
+        (libc::IPPROTO_IP, libc::IP_TOS) => {
+            if let Some(tos) = read::<u8>(data) {
+                return ControlMessage::Tos(tos);
+            }
+        }
+        (libc::SOL_UDP, libc::UDP_SEGMENT) => {
+            if let Some(size) = read::<u16>(data) {
+                return ControlMessage::SegmentSize(size);
+            }
+        }
+        (libc::SOL_SOCKET, libc::SCM_TIMESTAMPNS) => {
+            if let Some(ts) = read::<libc::timespec>(data) {
+                let since_epoch = Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+                return ControlMessage::Timestamp(SystemTime::UNIX_EPOCH + since_epoch);
+            }
+        }
+        (libc::SOL_UDP, libc::UDP_GRO) => {
+            if let Some(size) = read::<libc::c_int>(data) {
+                return ControlMessage::GroSegmentSize(size as u16);
+            }
+        }
+        _ => {}
+    }
+    ControlMessage::Other { level, ty, data }
+}
+
+/// An owned, aligned buffer of encoded control messages.
+///
+/// Control messages to send are added with [`ControlMessageBuf::push`]. The
+/// buffer is also used to return the control messages of a received message,
+/// which can be iterated with [`ControlMessageBuf::iter`].
+#[derive(Debug, Clone, Default)]
+pub struct ControlMessageBuf {
+    // Backed by `u64` so that each `cmsghdr` is suitably aligned.
+    buf: Vec<u64>,
+    len: usize,
+}
+
+impl ControlMessageBuf {
+    /// Creates an empty control message buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty control message buffer with space for at least `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: vec![0; capacity.div_ceil(8)],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bytes of encoded control messages.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer holds no control messages.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all control messages from the buffer.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Returns an iterator over the control messages in the buffer.
+    pub fn iter(&self) -> ControlMessages<'_> {
+        ControlMessages::new(&self.bytes()[..self.len])
+    }
+
+    /// Encodes a control message into the buffer.
+    ///
+    /// Returns an error for messages which can only be received, such as
+    /// [`ControlMessage::Timestamp`].
+    pub fn push(&mut self, msg: ControlMessage<'_>) -> io::Result<()> {
+        match msg {
+            ControlMessage::PacketInfo {
+                addr: IpAddr::V4(addr),
+                ifindex,
+            } => {
+                let info = libc::in_pktinfo {
+                    ipi_ifindex: ifindex as _,
+                    ipi_spec_dst: libc::in_addr {
+                        s_addr: u32::from(addr).to_be(),
+                    },
+                    ipi_addr: libc::in_addr { s_addr: 0 },
+                };
+                self.push_raw(libc::IPPROTO_IP, libc::IP_PKTINFO, as_bytes(&info));
+            }
+            ControlMessage::PacketInfo {
+                addr: IpAddr::V6(addr),
+                ifindex,
+            } => {
+                let info = libc::in6_pktinfo {
+                    ipi6_addr: libc::in6_addr {
+                        s6_addr: addr.octets(),
+                    },
+                    ipi6_ifindex: ifindex,
+                };
+                self.push_raw(libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, as_bytes(&info));
+            }
+            ControlMessage::Tos(tos) => {
+                let tos = tos as libc::c_int;
+                self.push_raw(libc::IPPROTO_IP, libc::IP_TOS, as_bytes(&tos));
+            }
+            ControlMessage::SegmentSize(size) => {
+                self.push_raw(libc::SOL_UDP, libc::UDP_SEGMENT, as_bytes(&size));
+            }
+            ControlMessage::Other { level, ty, data } => self.push_raw(level, ty, data),
+            ControlMessage::Timestamp(_) | ControlMessage::GroSegmentSize(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("control message can not be sent: {msg:?}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn push_raw(&mut self, level: i32, ty: i32, data: &[u8]) {
+        let start = self.len;
+        let end = start + cmsg_space(data.len());
+        if end > self.capacity() {
+            self.buf.resize(end.div_ceil(8), 0);
+        }
+        let hdr_len = cmsg_len(0);
+        let bytes = self.bytes_mut();
+        bytes[start..end].fill(0);
+        // Safety: `start` is aligned for `cmsghdr` as every message occupies a multiple of
+        // `CMSG_SPACE`, and the buffer was grown to hold the whole message.
+        unsafe {
+            let hdr = bytes.as_mut_ptr().add(start).cast::<libc::cmsghdr>();
+            (*hdr).cmsg_len = cmsg_len(data.len()) as _;
+            (*hdr).cmsg_level = level;
+            (*hdr).cmsg_type = ty;
+        }
+        bytes[start + hdr_len..start + hdr_len + data.len()].copy_from_slice(data);
+        self.len = end;
+    }
+
+    /// Returns the number of bytes the buffer can hold without reallocating.
+    pub(crate) fn capacity(&self) -> usize {
+        self.buf.len() * 8
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buf.as_mut_ptr().cast()
+    }
+
+    /// Set the number of bytes of encoded control messages.
+    ///
+    /// ### Safety
+    /// Callers should ensure that the first `len` bytes hold valid control messages.
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        assert!(len <= self.capacity());
+        self.len = len;
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // Safety: The buffer is valid for reads of `capacity()` bytes.
+        unsafe { std::slice::from_raw_parts(self.buf.as_ptr().cast(), self.capacity()) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // Safety: The buffer is valid for writes of `capacity()` bytes.
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr(), self.capacity()) }
+    }
+}
+
+impl<'a> IntoIterator for &'a ControlMessageBuf {
+    type Item = ControlMessage<'a>;
+    type IntoIter = ControlMessages<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Returns the bytes of a plain old data value.
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    // Safety: `T` is a plain value with no padding in the types used here.
+    unsafe { std::slice::from_raw_parts((value as *const T).cast(), std::mem::size_of::<T>()) }
+}
+
+// Read a `T` from the start of `data`, if it is large enough.
+fn read<T: Copy>(data: &[u8]) -> Option<T> {
+    if data.len() < std::mem::size_of::<T>() {
+        return None;
+    }
+    // Safety: `data` holds at least `size_of::<T>()` bytes, and is read
+    // unaligned as control data carries no alignment guarantees here.
+    Some(unsafe { data.as_ptr().cast::<T>().read_unaligned() })
+}
+
+// Equivalent to `CMSG_LEN`.
+pub(crate) fn cmsg_len(len: usize) -> usize {
+    // Safety: CMSG_LEN only performs arithmetic.
+    unsafe { libc::CMSG_LEN(len as u32) as usize }
+}
+
+// Equivalent to `CMSG_SPACE`.
+pub(crate) fn cmsg_space(len: usize) -> usize {
+    // Safety: CMSG_SPACE only performs arithmetic.
+    unsafe { libc::CMSG_SPACE(len as u32) as usize }
+}
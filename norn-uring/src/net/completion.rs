@@ -0,0 +1,249 @@
+//! Completion based stream I/O.
+//!
+//! Reads are served from a multishot receive into buffers selected from a
+//! [`BufRing`], copying into the caller's buffer as it is read. Writes are
+//! copied into an owned buffer which is submitted as a send operation, so the
+//! caller's buffer does not need to outlive the operation.
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+
+use crate::buf::{BufCursor, StableBuf};
+use crate::bufring::{BufRing, BufRingBuf};
+use crate::net::socket::{self, RecvMulti, RingStream, Socket};
+use crate::operation::Op;
+
+const LOG: &str = "norn_uring::net::completion";
+
+/// The number of bytes a [`CompletionWriter`] accepts while a send is in flight.
+pub(crate) const WRITE_BUF_LEN: usize = 64 * 1024;
+
+pin_project_lite::pin_project! {
+    /// The read half of a completion based stream.
+    pub(crate) struct CompletionReader {
+        #[pin]
+        recv: RingStream<'static, RecvMulti>,
+        // `chunk` is the buffer currently being read from, and `pos` the number of
+        // bytes of it already copied out.
+        chunk: Option<BufRingBuf>,
+        pos: usize,
+    }
+}
+
+impl CompletionReader {
+    pub(crate) fn new(socket: &Socket, ring: &BufRing) -> Self {
+        Self {
+            recv: socket.recv_multi_owned(ring),
+            chunk: None,
+            pos: 0,
+        }
+    }
+
+    pub(crate) fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        loop {
+            if let Some(chunk) = this.chunk {
+                let n = buf.remaining().min(chunk.len() - *this.pos);
+                buf.put_slice(&chunk[*this.pos..*this.pos + n]);
+                *this.pos += n;
+                if *this.pos == chunk.len() {
+                    // Return the buffer to the ring as soon as it is consumed.
+                    *this.chunk = None;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(this.recv.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    log::trace!(target: LOG, "read.recv {}", chunk.len());
+                    *this.chunk = Some(chunk);
+                    *this.pos = 0;
+                }
+                Some(Err(err)) => return Poll::Ready(Err(err)),
+                // The peer shut down the connection.
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+type ShutdownFuture = Pin<Box<dyn Future<Output = io::Result<()>>>>;
+type SendOp = Op<socket::Send<BufCursor<Vec<u8>>>>;
+
+pin_project_lite::pin_project! {
+    /// The write half of a completion based stream.
+    ///
+    /// At most one send is in flight at a time. Writes made while it is in flight
+    /// are copied into `queued`, up to [`WRITE_BUF_LEN`] bytes, and sent once it
+    /// completes.
+    pub(crate) struct CompletionWriter {
+        socket: Socket,
+        #[pin]
+        in_flight: Option<SendOp>,
+        queued: Vec<u8>,
+        // `spare` is the buffer of the last completed send, kept for reuse.
+        spare: Option<Vec<u8>>,
+        shutdown: Option<ShutdownFuture>,
+    }
+}
+
+impl CompletionWriter {
+    pub(crate) fn new(socket: Socket) -> Self {
+        Self {
+            socket,
+            in_flight: None,
+            queued: Vec::new(),
+            spare: None,
+            shutdown: None,
+        }
+    }
+
+    pub(crate) fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_vectored(cx, &[io::IoSlice::new(buf)])
+    }
+
+    pub(crate) fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        // An empty send would complete with 0, which reads as the peer not
+        // accepting any more data.
+        if bufs.iter().all(|buf| buf.is_empty()) {
+            return Poll::Ready(Ok(0));
+        }
+        let mut this = self.project();
+        loop {
+            match Self::poll_in_flight(this.socket, this.in_flight.as_mut(), this.spare, cx) {
+                Poll::Ready(Ok(())) if !this.queued.is_empty() => {
+                    Self::submit(
+                        this.socket,
+                        this.in_flight.as_mut(),
+                        this.queued,
+                        this.spare,
+                    );
+                    continue;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                _ => {}
+            }
+            if this.queued.len() >= WRITE_BUF_LEN {
+                // A send is in flight and will wake the task once it completes.
+                return Poll::Pending;
+            }
+            let mut n = 0;
+            for buf in bufs {
+                let len = buf.len().min(WRITE_BUF_LEN - this.queued.len());
+                this.queued.extend_from_slice(&buf[..len]);
+                n += len;
+                if this.queued.len() == WRITE_BUF_LEN {
+                    break;
+                }
+            }
+            if this.in_flight.is_none() {
+                Self::submit(
+                    this.socket,
+                    this.in_flight.as_mut(),
+                    this.queued,
+                    this.spare,
+                );
+                // Poll the new send so that it is pushed to the ring, rather than
+                // waiting for the next write or flush.
+                let in_flight = this.in_flight.as_mut();
+                if let Poll::Ready(Err(err)) =
+                    Self::poll_in_flight(this.socket, in_flight, this.spare, cx)
+                {
+                    return Poll::Ready(Err(err));
+                }
+            }
+            return Poll::Ready(Ok(n));
+        }
+    }
+
+    pub(crate) fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        loop {
+            ready!(Self::poll_in_flight(
+                this.socket,
+                this.in_flight.as_mut(),
+                this.spare,
+                cx
+            ))?;
+            if this.queued.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            Self::submit(
+                this.socket,
+                this.in_flight.as_mut(),
+                this.queued,
+                this.spare,
+            );
+        }
+    }
+
+    pub(crate) fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        let this = self.project();
+        let shutdown = this.shutdown.get_or_insert_with(|| {
+            let socket = this.socket.clone();
+            Box::pin(async move { socket.shutdown(std::net::Shutdown::Write).await })
+        });
+        shutdown.as_mut().poll(cx)
+    }
+
+    // Drives the in-flight send to completion, resubmitting the remainder of
+    // short sends. Returns `Ready` once no send is in flight.
+    fn poll_in_flight(
+        socket: &Socket,
+        mut in_flight: Pin<&mut Option<SendOp>>,
+        spare: &mut Option<Vec<u8>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        while let Some(op) = in_flight.as_mut().as_pin_mut() {
+            let (res, mut cursor) = ready!(op.poll(cx));
+            in_flight.set(None);
+            let n = res?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            cursor.consume(n);
+            if cursor.bytes_init() > 0 {
+                log::trace!(target: LOG, "write.short_send {n}");
+                in_flight.set(Some(socket.send(cursor)));
+            } else {
+                let mut buf = cursor.into_inner();
+                buf.clear();
+                *spare = Some(buf);
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn submit(
+        socket: &Socket,
+        mut in_flight: Pin<&mut Option<SendOp>>,
+        queued: &mut Vec<u8>,
+        spare: &mut Option<Vec<u8>>,
+    ) {
+        let buf = mem::replace(queued, spare.take().unwrap_or_default());
+        log::trace!(target: LOG, "write.send {}", buf.len());
+        in_flight.set(Some(socket.send(BufCursor::new(buf))));
+    }
+}
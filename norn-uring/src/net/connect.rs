@@ -0,0 +1,155 @@
+//! Connecting to one of several addresses.
+//!
+//! This implements the connection racing part of Happy Eyeballs Version 2
+//! ([RFC 8305]). Addresses are interleaved by family, and attempts are started
+//! one after the other, staggered by a delay. The first attempt to succeed
+//! wins and the remaining attempts are dropped, cancelling their operations.
+//!
+//! [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305
+use std::future::{poll_fn, Future};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use socket2::Type;
+
+use crate::net::socket::{Socket, SocketOptions};
+use crate::operation::Op;
+use crate::time::{self, Sleep};
+
+const LOG: &str = "norn_uring::net::connect";
+
+/// The delay between starting connection attempts recommended by RFC 8305.
+pub(crate) const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+type Attempt = Pin<Box<dyn Future<Output = io::Result<Socket>>>>;
+
+/// Connect to one of `addrs`, returning the first connection established.
+///
+/// Each attempt is limited to `attempt_timeout` if set. If every attempt fails,
+/// the error from the last attempt to fail is returned.
+pub(crate) async fn connect_any(
+    options: &SocketOptions,
+    addrs: Vec<SocketAddr>,
+    attempt_delay: Duration,
+    attempt_timeout: Option<Duration>,
+) -> io::Result<Socket> {
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no addresses to connect to",
+        ));
+    }
+    let mut pending = interleave(addrs).into_iter();
+    let mut attempts: Vec<Attempt> = vec![];
+    let mut delay: Option<Pin<Box<Op<Sleep>>>> = None;
+    let mut start_next = true;
+    let mut last_err = None;
+
+    poll_fn(|cx| loop {
+        if let Some(sleep) = &mut delay {
+            if sleep.as_mut().poll(cx).is_ready() {
+                delay = None;
+                start_next = true;
+            }
+        }
+        if start_next {
+            start_next = false;
+            if let Some(addr) = pending.next() {
+                log::trace!(target: LOG, "attempt.start {addr}");
+                attempts.push(attempt(options.clone(), addr, attempt_timeout));
+                if pending.len() > 0 {
+                    delay = Some(Box::pin(time::sleep(attempt_delay)));
+                    // Poll the new delay so that its timer is submitted.
+                    continue;
+                }
+            }
+        }
+
+        let mut i = 0;
+        while i < attempts.len() {
+            match attempts[i].as_mut().poll(cx) {
+                Poll::Ready(Ok(socket)) => return Poll::Ready(Ok(socket)),
+                Poll::Ready(Err(err)) => {
+                    log::trace!(target: LOG, "attempt.failed {err}");
+                    drop(attempts.swap_remove(i));
+                    last_err = Some(err);
+                    // A failed attempt starts the next one without waiting for the delay.
+                    start_next = true;
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+
+        if pending.len() == 0 {
+            if attempts.is_empty() {
+                return Poll::Ready(Err(last_err.take().unwrap()));
+            }
+            return Poll::Pending;
+        }
+        if !start_next {
+            return Poll::Pending;
+        }
+    })
+    .await
+}
+
+fn attempt(options: SocketOptions, addr: SocketAddr, timeout: Option<Duration>) -> Attempt {
+    Box::pin(async move {
+        let connect = async {
+            let socket = options.open(Type::STREAM, Some(addr)).await?;
+            socket.connect(addr).await?;
+            Ok(socket)
+        };
+        match timeout {
+            Some(timeout) => time::timeout(timeout, connect).await?,
+            None => connect.await,
+        }
+    })
+}
+
+/// Interleave addresses by family, starting with the family of the first address
+/// and otherwise preserving their order.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_v6 = addrs[0].is_ipv6();
+    let (mut first, mut second): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+    let mut interleaved = Vec::with_capacity(first.len() + second.len());
+    let (mut first, mut second) = (first.drain(..), second.drain(..));
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => return interleaved,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleave_families() {
+        let addrs: Vec<SocketAddr> = [
+            "[::1]:1",
+            "[::1]:2",
+            "[::1]:3",
+            "127.0.0.1:1",
+            "127.0.0.1:2",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+        let ports: Vec<_> = interleave(addrs)
+            .iter()
+            .map(|addr| (addr.is_ipv6(), addr.port()))
+            .collect();
+        assert_eq!(
+            vec![(true, 1), (false, 1), (true, 2), (false, 2), (true, 3)],
+            ports
+        );
+    }
+}
@@ -1,8 +1,18 @@
 //! Networking for Norn.
-mod socket;
+pub mod activation;
+pub mod cmsg;
+mod completion;
+mod connect;
+pub(crate) mod socket;
 mod tcp;
 mod udp;
+mod unix;
 
-pub use socket::Event;
-pub use tcp::{TcpListener, TcpSocket, TcpStream, TcpStreamReader, TcpStreamWriter};
-pub use udp::UdpSocket;
+pub use socket::{Event, RecvMsgBuf, RecvMsgInfo};
+pub use tcp::{
+    AcceptOptions, BoundedIncoming, ConnectionPermit, OwnedReadHalf, OwnedWriteHalf, ReadHalf,
+    RecvMultiStream, ReuniteError, TcpInfo, TcpListener, TcpSocket, TcpSocketBuilder, TcpStream,
+    TcpStreamReader, TcpStreamWriter, WriteHalf,
+};
+pub use udp::{RecvMsgMultiStream, UdpSocket, UdpSocketBuilder, RECVMSG_CONTROL_LEN};
+pub use unix::{UCred, UnixDatagram, UnixListener, UnixSocketAddr, UnixStream, MAX_FDS};
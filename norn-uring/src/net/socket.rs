@@ -1,37 +1,147 @@
 //! Socket operations.
 //!
 //! [Socket] is the core socket type
-//! used by both TCP and UDP sockets
+//! used by TCP, UDP and Unix sockets
+use std::future::Future;
 use std::io;
+use std::marker::PhantomData;
 use std::mem::{ManuallyDrop, MaybeUninit};
 use std::net::SocketAddr;
-use std::os::fd::FromRawFd;
+use std::ops::Range;
+use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::pin::Pin;
+use std::task::{ready, Context};
 
+use futures_core::Stream;
 use io_uring::squeue::Flags;
 use io_uring::{opcode, types};
 use libc::O_NONBLOCK;
 use socket2::{Domain, Protocol, SockAddr, Type};
 
-use crate::buf::{StableBuf, StableBufMut};
+use crate::buf::{StableBuf, StableBufMut, StableBufs, StableBufsMut};
 use crate::bufring::{BufRing, BufRingBuf};
 use crate::fd::NornFd;
+use crate::fixedbuf::FixedBuf;
+use crate::net::cmsg::{ControlMessageBuf, ControlMessages};
 use crate::operation::{Multishot, Op, Operation, Singleshot};
+use crate::pipe::{self, PipeReader, PipeWriter};
 
+const LOG: &str = "norn_uring::net::socket";
+
+/// A socket backed by a regular file descriptor.
+///
+/// Sockets are created by socket and accept operations or adopted from raw file
+/// descriptors, never from fixed files, so the std fd traits implemented on top
+/// of them always have a raw file descriptor to return.
 #[derive(Clone)]
 pub(crate) struct Socket {
     fd: NornFd,
     handle: crate::Handle,
 }
 
+/// Options which must be applied to a socket before it is bound or connected.
+///
+/// This backs the TCP and UDP socket builders.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SocketOptions {
+    pub(crate) local_addr: Option<SocketAddr>,
+    pub(crate) reuse_address: Option<bool>,
+    pub(crate) reuse_port: Option<bool>,
+    pub(crate) only_v6: Option<bool>,
+    pub(crate) device: Option<Vec<u8>>,
+    pub(crate) recv_buffer_size: Option<usize>,
+    pub(crate) send_buffer_size: Option<usize>,
+}
+
+impl SocketOptions {
+    /// Open a socket, apply the options and bind it to the local address if one was set.
+    ///
+    /// `peer` is used to pick the address family when no local address was set.
+    pub(crate) async fn open(
+        &self,
+        socket_type: Type,
+        peer: Option<SocketAddr>,
+    ) -> io::Result<Socket> {
+        let domain = match (self.local_addr, peer) {
+            (Some(addr), _) | (None, Some(addr)) => Domain::for_address(addr),
+            (None, None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "a local address is required to bind the socket",
+                ))
+            }
+        };
+        let socket = Socket::open(domain, socket_type, None).await?;
+        let s = socket.as_socket();
+        if let Some(reuse) = self.reuse_address {
+            s.set_reuse_address(reuse)?;
+        }
+        if let Some(reuse) = self.reuse_port {
+            socket.set_option(
+                libc::SOL_SOCKET,
+                libc::SO_REUSEPORT,
+                &(reuse as libc::c_int),
+            )?;
+        }
+        if let Some(only_v6) = self.only_v6 {
+            s.set_only_v6(only_v6)?;
+        }
+        if let Some(device) = &self.device {
+            socket.set_option_bytes(libc::SOL_SOCKET, libc::SO_BINDTODEVICE, device)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            s.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            s.set_send_buffer_size(size)?;
+        }
+        if let Some(addr) = self.local_addr {
+            s.bind(&SockAddr::from(addr))?;
+        }
+        Ok(socket)
+    }
+}
+
 impl Socket {
     pub(crate) fn from_fd(fd: NornFd) -> Self {
+        debug_assert!(fd.as_raw_fd().is_some(), "sockets are not fixed files");
         Self {
             fd,
             handle: crate::Handle::current(),
         }
     }
 
+    /// Adopt an open socket, putting it in non-blocking mode.
+    pub(crate) fn from_socket2(socket: socket2::Socket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self::from_fd(NornFd::from_fd(socket.into_raw_fd())))
+    }
+
+    /// Release ownership of the socket, see [`NornFd::into_raw_fd`].
+    ///
+    /// Fails, returning the socket, if the file descriptor is still referenced
+    /// by an operation.
+    pub(crate) fn into_socket2(self) -> Result<socket2::Socket, Self> {
+        let Self { fd, handle } = self;
+        match fd.into_raw_fd() {
+            // Safety: the file descriptor is no longer owned by `NornFd`.
+            Ok(fd) => Ok(unsafe { socket2::Socket::from_raw_fd(fd) }),
+            Err(fd) => Err(Self { fd, handle }),
+        }
+    }
+
+    /// Adopt a raw socket file descriptor.
+    ///
+    /// # Safety
+    /// `fd` must be an open socket which is not owned elsewhere.
+    pub(crate) unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_fd(NornFd::from_fd(fd))
+    }
+
+    pub(crate) fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd().expect("sockets are not fixed files")
+    }
+
     pub(crate) async fn open(
         domain: Domain,
         socket_type: Type,
@@ -53,13 +163,34 @@ impl Socket {
         domain: Domain,
         socket_type: Type,
     ) -> io::Result<Self> {
-        let addr = SockAddr::from(addr);
+        Self::bind_addr(&SockAddr::from(addr), domain, socket_type).await
+    }
+
+    pub(crate) async fn bind_addr(
+        addr: &SockAddr,
+        domain: Domain,
+        socket_type: Type,
+    ) -> io::Result<Self> {
         let socket = Self::open(domain, socket_type, None).await?;
         let s = socket.as_socket();
-        s.bind(&addr)?;
+        s.bind(addr)?;
         Ok(socket)
     }
 
+    /// Create a pair of connected sockets.
+    pub(crate) fn pair(domain: Domain, socket_type: Type) -> io::Result<(Self, Self)> {
+        let ty: i32 = socket_type.into();
+        let ty = ty | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+        let mut fds = [0; 2];
+        // Safety: `fds` is valid for writes of two file descriptors.
+        if unsafe { libc::socketpair(domain.into(), ty, 0, fds.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let a = Self::from_fd(NornFd::from_fd(fds[0]));
+        let b = Self::from_fd(NornFd::from_fd(fds[1]));
+        Ok((a, b))
+    }
+
     pub(crate) fn listen(&self, backlog: u32) -> io::Result<()> {
         let s = self.as_socket();
         s.listen(backlog as _)?;
@@ -67,6 +198,11 @@ impl Socket {
     }
 
     pub(crate) async fn accept(&self) -> io::Result<(Self, SocketAddr)> {
+        let (socket, addr) = self.accept_addr().await?;
+        Ok((socket, addr.as_socket().unwrap()))
+    }
+
+    pub(crate) async fn accept_addr(&self) -> io::Result<(Self, SockAddr)> {
         let op = Accept::<false>::new(self.fd.clone());
         let (fd, addr) = self.handle.submit(op).await?;
         let socket = Self::from_fd(fd);
@@ -79,6 +215,10 @@ impl Socket {
     }
 
     pub(crate) async fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        self.connect_addr(SockAddr::from(addr)).await
+    }
+
+    pub(crate) async fn connect_addr(&self, addr: SockAddr) -> io::Result<()> {
         let op = Connect::new(self.fd.clone(), addr);
         self.handle.submit(op).await?;
         Ok(())
@@ -105,6 +245,66 @@ impl Socket {
         self.handle.submit(op).await
     }
 
+    pub(crate) async fn send_msg<V>(
+        &self,
+        bufs: V,
+        addr: Option<SockAddr>,
+        control: &ControlMessageBuf,
+    ) -> (io::Result<usize>, V)
+    where
+        V: StableBufs,
+    {
+        let op = SendMsg::new(self.fd.clone(), bufs, addr, control.clone());
+        self.handle.submit(op).await
+    }
+
+    pub(crate) async fn send_msg_zc<V>(
+        &self,
+        bufs: V,
+        addr: Option<SockAddr>,
+        control: &ControlMessageBuf,
+    ) -> (io::Result<usize>, V)
+    where
+        V: StableBufs,
+    {
+        let op = SendMsgZc::new(self.fd.clone(), bufs, addr, control.clone());
+        self.handle.submit(op).await
+    }
+
+    pub(crate) async fn recv_msg<V>(
+        &self,
+        bufs: V,
+        control_len: usize,
+        flags: u32,
+    ) -> (io::Result<RecvMsgInfo>, V)
+    where
+        V: StableBufsMut,
+    {
+        let op = RecvMsg::new(self.fd.clone(), bufs, control_len, flags);
+        self.handle.submit(op).await
+    }
+
+    pub(crate) fn recv_multi<'a>(&'a self, ring: &'a BufRing) -> RingStream<'a, RecvMulti> {
+        let op = RecvMulti::new(self.fd.clone(), ring.clone());
+        RingStream::new(self, ring, op)
+    }
+
+    /// Like [`Socket::recv_multi`], but the stream holds its own reference to
+    /// the socket.
+    pub(crate) fn recv_multi_owned(&self, ring: &BufRing) -> RingStream<'static, RecvMulti> {
+        let op = RecvMulti::new(self.fd.clone(), ring.clone());
+        RingStream::new(self, ring, op)
+    }
+
+    pub(crate) fn recvmsg_multi<'a>(
+        &'a self,
+        ring: &'a BufRing,
+        control_len: u32,
+    ) -> RingStream<'a, RecvMsgMulti> {
+        let op = RecvMsgMulti::new(self.fd.clone(), ring.clone(), control_len);
+        RingStream::new(self, ring, op)
+    }
+
     pub(crate) fn recv<B>(&self, buf: B) -> Op<Recv<B>>
     where
         B: StableBufMut + 'static,
@@ -121,6 +321,38 @@ impl Socket {
         self.handle.submit(op)
     }
 
+    pub(crate) fn send_zc<B>(&self, buf: B, addr: Option<SocketAddr>) -> Op<SendZc<B>>
+    where
+        B: StableBuf + 'static,
+    {
+        let op = SendZc::new(self.fd.clone(), buf, addr);
+        self.handle.submit(op)
+    }
+
+    pub(crate) fn recv_fixed(&self, buf: FixedBuf) -> Op<RecvFixed> {
+        let op = RecvFixed::new(self.fd.clone(), buf);
+        self.handle.submit(op)
+    }
+
+    pub(crate) async fn splice_to(&self, pipe: &PipeWriter, len: u32) -> io::Result<usize> {
+        let wait = (&self.fd, READ_FLAGS);
+        pipe::splice(&self.handle, &self.fd, -1, pipe.fd(), -1, len, wait).await
+    }
+
+    pub(crate) async fn splice_from(&self, pipe: &PipeReader, len: u32) -> io::Result<usize> {
+        let wait = (&self.fd, WRITE_FLAGS);
+        pipe::splice(&self.handle, pipe.fd(), -1, &self.fd, -1, len, wait).await
+    }
+
+    pub(crate) fn fd(&self) -> &NornFd {
+        &self.fd
+    }
+
+    pub(crate) fn send_fixed(&self, buf: FixedBuf) -> Op<SendFixed> {
+        let op = SendFixed::new(self.fd.clone(), buf);
+        self.handle.submit(op)
+    }
+
     pub(crate) async fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
         let how = match how {
             std::net::Shutdown::Read => libc::SHUT_RD,
@@ -144,14 +376,60 @@ impl Socket {
         Ok(self.as_socket().peer_addr()?.as_socket().unwrap())
     }
 
-    pub(crate) fn as_socket(&self) -> ManuallyDrop<socket2::Socket> {
-        match self.fd.kind() {
-            crate::fd::FdKind::Fd(fd) => {
-                let sock = unsafe { socket2::Socket::from_raw_fd(fd.0) };
-                ManuallyDrop::new(sock)
-            }
-            crate::fd::FdKind::Fixed(_) => unimplemented!(),
+    pub(crate) fn local_sockaddr(&self) -> io::Result<SockAddr> {
+        self.as_socket().local_addr()
+    }
+
+    pub(crate) fn peer_sockaddr(&self) -> io::Result<SockAddr> {
+        self.as_socket().peer_addr()
+    }
+
+    /// Get a socket option with `getsockopt`.
+    pub(crate) fn get_option<T: Copy>(&self, level: i32, name: i32) -> io::Result<T> {
+        let fd = self.as_socket().as_raw_fd();
+        let mut value = MaybeUninit::<T>::zeroed();
+        let mut len = std::mem::size_of::<T>() as libc::socklen_t;
+        // Safety: `value` is valid for writes of `len` bytes.
+        let res = unsafe { libc::getsockopt(fd, level, name, value.as_mut_ptr().cast(), &mut len) };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
         }
+        // Safety: The value was zero initialized, and possibly written by the kernel.
+        Ok(unsafe { value.assume_init() })
+    }
+
+    /// Set a socket option with `setsockopt`.
+    pub(crate) fn set_option<T>(&self, level: i32, name: i32, value: &T) -> io::Result<()> {
+        // Safety: `value` points to a valid `T`.
+        let value = unsafe {
+            std::slice::from_raw_parts((value as *const T).cast(), std::mem::size_of::<T>())
+        };
+        self.set_option_bytes(level, name, value)
+    }
+
+    /// Set a socket option with `setsockopt` from a byte slice.
+    pub(crate) fn set_option_bytes(&self, level: i32, name: i32, value: &[u8]) -> io::Result<()> {
+        let fd = self.as_socket().as_raw_fd();
+        // Safety: `value` is valid for reads of its length.
+        let res = unsafe {
+            libc::setsockopt(
+                fd,
+                level,
+                name,
+                value.as_ptr().cast(),
+                value.len() as libc::socklen_t,
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn as_socket(&self) -> ManuallyDrop<socket2::Socket> {
+        // Safety: the socket is not dropped, so the file descriptor stays owned by `self`.
+        let sock = unsafe { socket2::Socket::from_raw_fd(self.as_raw_fd()) };
+        ManuallyDrop::new(sock)
     }
 
     pub(crate) async fn close(self) -> io::Result<()> {
@@ -372,6 +650,302 @@ where
     }
 }
 
+/// Vectored `sendmsg` with control messages.
+pub(crate) struct SendMsg<V> {
+    fd: NornFd,
+    bufs: V,
+    addr: Option<SockAddr>,
+    control: ControlMessageBuf,
+    iovecs: Vec<libc::iovec>,
+    msghdr: libc::msghdr,
+}
+
+impl<V> SendMsg<V>
+where
+    V: StableBufs,
+{
+    pub(crate) fn new(
+        fd: NornFd,
+        bufs: V,
+        addr: Option<SockAddr>,
+        control: ControlMessageBuf,
+    ) -> Self {
+        Self {
+            fd,
+            bufs,
+            addr,
+            control,
+            iovecs: Vec::new(),
+            // Safety: An all-zero msghdr is valid, it is initialized in configure.
+            msghdr: unsafe { std::mem::zeroed() },
+        }
+    }
+}
+
+impl<V> SendMsg<V>
+where
+    V: StableBufs,
+{
+    /// Fill in the msghdr, which must not move until the operation completes.
+    fn prepare(&mut self) -> *const libc::msghdr {
+        let this = self;
+        this.iovecs = this
+            .bufs
+            .as_bufs()
+            .iter()
+            .map(|buf| libc::iovec {
+                iov_base: buf.stable_ptr() as *mut _,
+                iov_len: buf.bytes_init(),
+            })
+            .collect();
+        this.msghdr.msg_iov = this.iovecs.as_mut_ptr();
+        this.msghdr.msg_iovlen = this.iovecs.len() as _;
+        if let Some(addr) = &this.addr {
+            this.msghdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            this.msghdr.msg_namelen = addr.len() as _;
+        }
+        if !this.control.is_empty() {
+            this.msghdr.msg_control = this.control.as_mut_ptr().cast();
+            this.msghdr.msg_controllen = this.control.len() as _;
+        }
+        &this.msghdr as *const _
+    }
+}
+
+impl<V> Operation for SendMsg<V>
+where
+    V: StableBufs,
+{
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        // Safety: the msghdr points into `self`, which is not moved.
+        let this = unsafe { self.get_unchecked_mut() };
+        let msghdr = this.prepare();
+        match this.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::SendMsg::new(*fd, msghdr).build(),
+            crate::fd::FdKind::Fixed(fd) => opcode::SendMsg::new(*fd, msghdr).build(),
+        }
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl<V> Singleshot for SendMsg<V>
+where
+    V: StableBufs,
+{
+    type Output = (io::Result<usize>, V);
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        (result.result.map(|v| v as usize), self.bufs)
+    }
+}
+
+/// Zero-copy vectored `sendmsg` with control messages.
+///
+/// The completions are handled as for [`SendZc`], the buffers are only
+/// returned once the kernel no longer references them.
+pub(crate) struct SendMsgZc<V> {
+    msg: SendMsg<V>,
+    // `result` holds the result of the send until the notification arrives.
+    result: Option<io::Result<u32>>,
+}
+
+impl<V> SendMsgZc<V>
+where
+    V: StableBufs,
+{
+    pub(crate) fn new(
+        fd: NornFd,
+        bufs: V,
+        addr: Option<SockAddr>,
+        control: ControlMessageBuf,
+    ) -> Self {
+        Self {
+            msg: SendMsg::new(fd, bufs, addr, control),
+            result: None,
+        }
+    }
+}
+
+impl<V> Operation for SendMsgZc<V>
+where
+    V: StableBufs,
+{
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        // Safety: the msghdr points into `self`, which is not moved.
+        let this = unsafe { self.get_unchecked_mut() };
+        let msghdr = this.msg.prepare();
+        match this.msg.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::SendMsgZc::new(*fd, msghdr).build(),
+            crate::fd::FdKind::Fixed(fd) => opcode::SendMsgZc::new(*fd, msghdr).build(),
+        }
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl<V> Singleshot for SendMsgZc<V>
+where
+    V: StableBufs,
+{
+    type Output = (io::Result<usize>, V);
+
+    fn update(&mut self, result: crate::operation::CQEResult) {
+        self.result = Some(result.result);
+    }
+
+    fn complete(mut self, result: crate::operation::CQEResult) -> Self::Output {
+        let res = zc_result(&mut self.result, result);
+        (res.map(|v| v as usize), self.msg.bufs)
+    }
+}
+
+/// Vectored `recvmsg` with control messages.
+pub(crate) struct RecvMsg<V> {
+    fd: NornFd,
+    bufs: V,
+    flags: u32,
+    addr: SockAddr,
+    control: ControlMessageBuf,
+    iovecs: Vec<libc::iovec>,
+    msghdr: libc::msghdr,
+}
+
+impl<V> RecvMsg<V>
+where
+    V: StableBufsMut,
+{
+    pub(crate) fn new(fd: NornFd, bufs: V, control_len: usize, flags: u32) -> Self {
+        // Safety: We won't read from the socket addr until it's initialized.
+        let addr = unsafe { SockAddr::try_init(|_, _| Ok(())) }.unwrap().1;
+        Self {
+            fd,
+            bufs,
+            flags,
+            addr,
+            control: ControlMessageBuf::with_capacity(control_len),
+            iovecs: Vec::new(),
+            // Safety: An all-zero msghdr is valid, it is initialized in configure.
+            msghdr: unsafe { std::mem::zeroed() },
+        }
+    }
+}
+
+impl<V> Operation for RecvMsg<V>
+where
+    V: StableBufsMut,
+{
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        this.iovecs = this
+            .bufs
+            .as_bufs_mut()
+            .iter_mut()
+            .map(|buf| libc::iovec {
+                iov_base: buf.stable_ptr_mut().cast(),
+                iov_len: buf.bytes_remaining(),
+            })
+            .collect();
+        this.msghdr.msg_iov = this.iovecs.as_mut_ptr();
+        this.msghdr.msg_iovlen = this.iovecs.len() as _;
+        this.msghdr.msg_name = this.addr.as_ptr() as *mut libc::c_void;
+        this.msghdr.msg_namelen = this.addr.len() as _;
+        this.msghdr.msg_control = this.control.as_mut_ptr().cast();
+        this.msghdr.msg_controllen = this.control.capacity() as _;
+
+        let msghdr = &mut this.msghdr as *mut _;
+        match this.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::RecvMsg::new(*fd, msghdr),
+            crate::fd::FdKind::Fixed(fd) => opcode::RecvMsg::new(*fd, msghdr),
+        }
+        .flags(this.flags)
+        .build()
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl<V> Singleshot for RecvMsg<V>
+where
+    V: StableBufsMut,
+{
+    type Output = (io::Result<RecvMsgInfo>, V);
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        let mut this = self;
+        let len = match result.result {
+            Ok(n) => n as usize,
+            Err(err) => return (Err(err), this.bufs),
+        };
+        // Distribute the received bytes across the buffers in order.
+        let mut remaining = len;
+        for buf in this.bufs.as_bufs_mut() {
+            let n = remaining.min(buf.bytes_remaining());
+            unsafe { buf.set_init(n) };
+            remaining -= n;
+        }
+        // Safety: The kernel updated the msghdr with the length of the control
+        // messages it wrote.
+        unsafe { this.control.set_len(this.msghdr.msg_controllen) };
+        // Safety: The kernel initialized the address and updated its length.
+        let addr = unsafe { SockAddr::new(this.addr.as_storage(), this.msghdr.msg_namelen) };
+        let info = RecvMsgInfo {
+            len,
+            addr,
+            flags: this.msghdr.msg_flags,
+            control: this.control,
+        };
+        (Ok(info), this.bufs)
+    }
+}
+
+/// The result of a message received with `recvmsg`.
+#[derive(Debug)]
+pub struct RecvMsgInfo {
+    len: usize,
+    addr: SockAddr,
+    flags: i32,
+    control: ControlMessageBuf,
+}
+
+impl RecvMsgInfo {
+    /// Returns the number of bytes received.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if an empty message was received.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the address the message was sent from, if any.
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.addr.as_socket()
+    }
+
+    pub(crate) fn source_addr(&self) -> &SockAddr {
+        &self.addr
+    }
+
+    /// Returns true if the message was larger than the buffers and was truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.flags & libc::MSG_TRUNC != 0
+    }
+
+    /// Returns true if the control messages did not fit in the space reserved for
+    /// them and were truncated.
+    pub fn is_control_truncated(&self) -> bool {
+        self.flags & libc::MSG_CTRUNC != 0
+    }
+
+    /// Returns the control messages received with the message.
+    pub fn control_messages(&self) -> &ControlMessageBuf {
+        &self.control
+    }
+}
+
 #[derive(Debug)]
 pub struct RecvFromRing {
     fd: NornFd,
@@ -434,16 +1008,330 @@ impl Singleshot for RecvFromRing {
     }
 }
 
+pin_project_lite::pin_project! {
+    /// A stream over a multishot receive operation using buffers from a
+    /// buffer ring.
+    ///
+    /// The kernel terminates a multishot receive when it cannot deliver a
+    /// completion, most commonly because the ring ran out of buffers
+    /// (`ENOBUFS`). The stream transparently re-arms the operation, first
+    /// waiting for a buffer to be returned to the ring if none are available.
+    /// The stream ends once the operation yields `None`.
+    pub(crate) struct RingStream<'a, T: 'static> {
+        handle: crate::Handle,
+        ring: BufRing,
+        op: T,
+        #[pin]
+        current: Option<Op<T>>,
+        returned: Option<Pin<Box<dyn Future<Output = ()>>>>,
+        done: bool,
+        // The stream borrows the socket, although the operation keeps its own
+        // reference to the file descriptor.
+        _socket: PhantomData<&'a Socket>,
+    }
+}
+
+impl<T: 'static> RingStream<'_, T> {
+    fn new(socket: &Socket, ring: &BufRing, op: T) -> Self {
+        Self {
+            handle: socket.handle.clone(),
+            ring: ring.clone(),
+            op,
+            current: None,
+            returned: None,
+            done: false,
+            _socket: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> std::fmt::Debug for RingStream<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingStream")
+            .field("ring", &self.ring)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<T, U> Stream for RingStream<'_, T>
+where
+    T: Multishot<Item = io::Result<Option<U>>> + Clone + 'static,
+{
+    type Item = io::Result<U>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+
+        let mut this = self.project();
+        loop {
+            if *this.done {
+                return Poll::Ready(None);
+            }
+            if let Some(returned) = this.returned {
+                ready!(returned.as_mut().poll(cx));
+                *this.returned = None;
+            }
+            if let Some(current) = this.current.as_mut().as_pin_mut() {
+                match ready!(current.poll_next(cx)) {
+                    Some(Ok(Some(item))) => return Poll::Ready(Some(Ok(item))),
+                    Some(Ok(None)) => {
+                        log::trace!(target: LOG, "ring_stream.eof");
+                        this.current.set(None);
+                        *this.done = true;
+                        return Poll::Ready(None);
+                    }
+                    Some(Err(err)) if err.raw_os_error() == Some(libc::ENOBUFS) => {
+                        log::trace!(target: LOG, "ring_stream.no_buffers");
+                        this.current.set(None);
+                        if this.ring.available() == 0 {
+                            // Wait for a buffer to be returned before re-arming.
+                            let ring = this.ring.clone();
+                            *this.returned = Some(Box::pin(async move { ring.returned().await }));
+                        }
+                        continue;
+                    }
+                    Some(Err(err)) => {
+                        this.current.set(None);
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    None => {
+                        log::trace!(target: LOG, "ring_stream.rearm");
+                        this.current.set(None);
+                    }
+                }
+            }
+            if this.returned.is_none() {
+                let op = this.handle.submit(this.op.clone());
+                this.current.set(Some(op));
+            }
+        }
+    }
+}
+
+/// Multishot receive into buffers selected from a buffer ring.
+///
+/// Each completion yields a buffer, or `None` once the peer has shut down
+/// the connection.
+#[derive(Clone)]
+pub(crate) struct RecvMulti {
+    fd: NornFd,
+    ring: BufRing,
+}
+
+impl RecvMulti {
+    pub(crate) fn new(fd: NornFd, ring: BufRing) -> Self {
+        Self { fd, ring }
+    }
+}
+
+impl Operation for RecvMulti {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let bgid = self.ring.bgid();
+        match self.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::RecvMulti::new(*fd, bgid).build(),
+            crate::fd::FdKind::Fixed(fd) => opcode::RecvMulti::new(*fd, bgid).build(),
+        }
+    }
+
+    fn cleanup(&mut self, res: crate::operation::CQEResult) {
+        if let Ok(n) = res.result {
+            if io_uring::cqueue::buffer_select(res.flags).is_some() {
+                drop(self.ring.get_buf(n, res.flags));
+            }
+        }
+    }
+}
+
+impl Multishot for RecvMulti {
+    type Item = io::Result<Option<BufRingBuf>>;
+
+    fn update(&mut self, result: crate::operation::CQEResult) -> Self::Item {
+        let n = result.result?;
+        // A completion without a buffer indicates EOF.
+        if io_uring::cqueue::buffer_select(result.flags).is_none() {
+            return Ok(None);
+        }
+        self.ring.get_buf(n, result.flags).map(Some)
+    }
+}
+
+/// Multishot `recvmsg` into buffers selected from a buffer ring.
+///
+/// The kernel writes an `io_uring_recvmsg_out` header followed by the source
+/// address, control data and payload into each selected buffer.
+pub(crate) struct RecvMsgMulti {
+    fd: NornFd,
+    ring: BufRing,
+    msghdr: libc::msghdr,
+}
+
+impl Clone for RecvMsgMulti {
+    fn clone(&self) -> Self {
+        Self::new(
+            self.fd.clone(),
+            self.ring.clone(),
+            self.msghdr.msg_controllen as u32,
+        )
+    }
+}
+
+impl RecvMsgMulti {
+    pub(crate) fn new(fd: NornFd, ring: BufRing, control_len: u32) -> Self {
+        // Safety: An all-zero msghdr is valid. Only the name and control lengths are used by
+        // multishot recvmsg, to size the respective regions of each selected buffer.
+        let mut msghdr: libc::msghdr = unsafe { std::mem::zeroed() };
+        msghdr.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as _;
+        msghdr.msg_controllen = control_len as _;
+        Self { fd, ring, msghdr }
+    }
+}
+
+impl Operation for RecvMsgMulti {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let msghdr = &self.msghdr as *const _;
+        let bgid = self.ring.bgid();
+        match self.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::RecvMsgMulti::new(*fd, msghdr, bgid).build(),
+            crate::fd::FdKind::Fixed(fd) => opcode::RecvMsgMulti::new(*fd, msghdr, bgid).build(),
+        }
+    }
+
+    fn cleanup(&mut self, res: crate::operation::CQEResult) {
+        if let Ok(n) = res.result {
+            if io_uring::cqueue::buffer_select(res.flags).is_some() {
+                drop(self.ring.get_buf(n, res.flags));
+            }
+        }
+    }
+}
+
+impl Multishot for RecvMsgMulti {
+    type Item = io::Result<Option<RecvMsgBuf>>;
+
+    fn update(&mut self, result: crate::operation::CQEResult) -> Self::Item {
+        let n = result.result?;
+        if io_uring::cqueue::buffer_select(result.flags).is_none() {
+            return Ok(None);
+        }
+        let buf = self.ring.get_buf(n, result.flags)?;
+        RecvMsgBuf::new(buf, &self.msghdr).map(Some)
+    }
+}
+
+/// A message received with `recvmsg`, stored in a buffer from a buffer ring.
+///
+/// Provides access to the payload, source address and control messages of
+/// the message. The buffer is returned to the ring when this is dropped.
+pub struct RecvMsgBuf {
+    buf: BufRingBuf,
+    name: Range<usize>,
+    control: Range<usize>,
+    payload: Range<usize>,
+    name_truncated: bool,
+    control_truncated: bool,
+    payload_truncated: bool,
+}
+
+impl std::fmt::Debug for RecvMsgBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecvMsgBuf")
+            .field("source", &self.source())
+            .field("len", &self.payload.len())
+            .finish()
+    }
+}
+
+impl RecvMsgBuf {
+    fn new(buf: BufRingBuf, msghdr: &libc::msghdr) -> io::Result<Self> {
+        let out = types::RecvMsgOut::parse(&buf, msghdr).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "recvmsg buffer is too small for the message header",
+            )
+        })?;
+        let start = buf.as_ptr() as usize;
+        let range = |data: &[u8]| {
+            let offset = data.as_ptr() as usize - start;
+            offset..offset + data.len()
+        };
+        let (name, control, payload) = (
+            range(out.name_data()),
+            range(out.control_data()),
+            range(out.payload_data()),
+        );
+        let (name_truncated, control_truncated, payload_truncated) = (
+            out.is_name_data_truncated(),
+            out.is_control_data_truncated(),
+            out.is_payload_truncated(),
+        );
+        Ok(Self {
+            buf,
+            name,
+            control,
+            payload,
+            name_truncated,
+            control_truncated,
+            payload_truncated,
+        })
+    }
+
+    /// Returns the payload of the message.
+    pub fn payload(&self) -> &[u8] {
+        &self.buf[self.payload.clone()]
+    }
+
+    /// Returns the address the message was sent from, if any.
+    pub fn source(&self) -> Option<SocketAddr> {
+        let name = &self.buf[self.name.clone()];
+        if name.is_empty() || self.name_truncated {
+            return None;
+        }
+        // Safety: The kernel wrote a socket address of `name.len()` bytes, which fits in the
+        // storage as the name region of the buffer is the size of `sockaddr_storage`.
+        let (_, addr) = unsafe {
+            SockAddr::try_init(|storage, len| {
+                std::ptr::copy_nonoverlapping(name.as_ptr(), storage.cast(), name.len());
+                *len = name.len() as _;
+                Ok(())
+            })
+        }
+        .ok()?;
+        addr.as_socket()
+    }
+
+    /// Returns true if the payload was larger than the buffer and was truncated.
+    pub fn is_payload_truncated(&self) -> bool {
+        self.payload_truncated
+    }
+
+    /// Returns true if the control data was larger than the space reserved for it
+    /// and was truncated.
+    pub fn is_control_truncated(&self) -> bool {
+        self.control_truncated
+    }
+
+    /// Returns an iterator over the control messages received with the message.
+    pub fn control_messages(&self) -> ControlMessages<'_> {
+        ControlMessages::new(&self.buf[self.control.clone()])
+    }
+}
+
 pub(crate) struct Accept<const MULTI: bool> {
     fd: NornFd,
     addr: SockAddr,
+    addrlen: libc::socklen_t,
 }
 
 impl<const MULTI: bool> Accept<MULTI> {
     pub(crate) fn new(fd: NornFd) -> Self {
         // Safety: We won't read from the socket addr until it's initialized.
         let addr = unsafe { SockAddr::try_init(|_, _| Ok(())) }.unwrap().1;
-        Self { fd, addr }
+        let addrlen = addr.len();
+        Self { fd, addr, addrlen }
     }
 }
 
@@ -457,7 +1345,7 @@ impl<const MULTI: bool> Operation for Accept<MULTI> {
                 if MULTI {
                     opcode::AcceptMulti::new(*fd).flags(O_NONBLOCK).build()
                 } else {
-                    opcode::Accept::new(*fd, this.addr.as_ptr() as *mut _, this.addr.len() as _)
+                    opcode::Accept::new(*fd, this.addr.as_ptr() as *mut _, &mut this.addrlen)
                         .flags(O_NONBLOCK)
                         .build()
                 }
@@ -466,7 +1354,7 @@ impl<const MULTI: bool> Operation for Accept<MULTI> {
                 if MULTI {
                     opcode::AcceptMulti::new(*fd).flags(O_NONBLOCK).build()
                 } else {
-                    opcode::Accept::new(*fd, this.addr.as_ptr() as *mut _, this.addr.len() as _)
+                    opcode::Accept::new(*fd, this.addr.as_ptr() as *mut _, &mut this.addrlen)
                         .flags(O_NONBLOCK)
                         .build()
                 }
@@ -482,11 +1370,12 @@ impl<const MULTI: bool> Operation for Accept<MULTI> {
 }
 
 impl Singleshot for Accept<false> {
-    type Output = io::Result<(NornFd, SocketAddr)>;
+    type Output = io::Result<(NornFd, SockAddr)>;
 
     fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
         let fd = result.result?;
-        let addr = self.addr.as_socket().unwrap();
+        // Safety: The kernel initialized the address and updated its length.
+        let addr = unsafe { SockAddr::new(self.addr.as_storage(), self.addrlen) };
         Ok((NornFd::from_fd(fd as i32), addr))
     }
 }
@@ -506,8 +1395,7 @@ struct Connect {
 }
 
 impl Connect {
-    pub(crate) fn new(fd: NornFd, addr: SocketAddr) -> Self {
-        let addr = SockAddr::from(addr);
+    pub(crate) fn new(fd: NornFd, addr: SockAddr) -> Self {
         Self { fd, addr }
     }
 }
@@ -667,6 +1555,191 @@ where
     }
 }
 
+/// Zero-copy send, optionally to a destination address.
+///
+/// The kernel posts two completions for a zero-copy send. The first carries
+/// the result and has the more flag set, the second is a notification that
+/// the kernel no longer references the buffer. The buffer is only returned
+/// once the notification has been received.
+pub struct SendZc<B> {
+    fd: NornFd,
+    buf: B,
+    addr: Option<SockAddr>,
+    // `result` holds the result of the send until the notification arrives.
+    result: Option<io::Result<u32>>,
+}
+
+impl<B> std::fmt::Debug for SendZc<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendZc")
+            .field("result", &self.result)
+            .finish()
+    }
+}
+
+impl<B> SendZc<B>
+where
+    B: StableBuf,
+{
+    pub(crate) fn new(fd: NornFd, buf: B, addr: Option<SocketAddr>) -> Self {
+        Self {
+            fd,
+            buf,
+            addr: addr.map(SockAddr::from),
+            result: None,
+        }
+    }
+}
+
+impl<B> Operation for SendZc<B>
+where
+    B: StableBuf,
+{
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let ptr = self.buf.stable_ptr();
+        let len = self.buf.bytes_init() as u32;
+        let (addr, addr_len) = match &self.addr {
+            Some(addr) => (addr.as_ptr(), addr.len()),
+            None => (std::ptr::null(), 0),
+        };
+        match self.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::SendZc::new(*fd, ptr, len)
+                .dest_addr(addr)
+                .dest_addr_len(addr_len)
+                .build(),
+            crate::fd::FdKind::Fixed(fd) => opcode::SendZc::new(*fd, ptr, len)
+                .dest_addr(addr)
+                .dest_addr_len(addr_len)
+                .build(),
+        }
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl<B> Singleshot for SendZc<B>
+where
+    B: StableBuf,
+{
+    type Output = (io::Result<usize>, B);
+
+    fn update(&mut self, result: crate::operation::CQEResult) {
+        // The result of the send, the notification follows.
+        self.result = Some(result.result);
+    }
+
+    fn complete(mut self, result: crate::operation::CQEResult) -> Self::Output {
+        let res = zc_result(&mut self.result, result);
+        (res.map(|v| v as usize), self.buf)
+    }
+}
+
+/// Returns the result of a zero-copy send from its final completion.
+///
+/// If the send failed before the buffer was used, there is no notification and
+/// the final completion carries the result. Otherwise the result was stored by
+/// `update` when the first completion arrived.
+fn zc_result(
+    pending: &mut Option<io::Result<u32>>,
+    result: crate::operation::CQEResult,
+) -> io::Result<u32> {
+    if !result.notif() {
+        return result.result;
+    }
+    pending.take().unwrap_or_else(|| {
+        Err(io::Error::other(
+            "zero-copy notification received before the send result",
+        ))
+    })
+}
+
+/// Receive into a registered buffer.
+///
+/// Sockets do not have a file position, so this is issued as a
+/// `ReadFixed` with the offset ignored by the kernel.
+#[derive(Debug)]
+pub struct RecvFixed {
+    fd: NornFd,
+    buf: FixedBuf,
+}
+
+impl RecvFixed {
+    pub(crate) fn new(fd: NornFd, buf: FixedBuf) -> Self {
+        Self { fd, buf }
+    }
+}
+
+impl Operation for RecvFixed {
+    fn configure(mut self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let ptr = self.buf.stable_ptr_mut();
+        let len = self.buf.bytes_remaining();
+        let index = self.buf.buf_index();
+        match self.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::ReadFixed::new(*fd, ptr, len as _, index).build(),
+            crate::fd::FdKind::Fixed(fd) => {
+                opcode::ReadFixed::new(*fd, ptr, len as _, index).build()
+            }
+        }
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl Singleshot for RecvFixed {
+    type Output = (io::Result<usize>, FixedBuf);
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        match result.result {
+            Ok(bytes_read) => {
+                let mut buf = self.buf;
+                unsafe { buf.set_init(bytes_read as usize) };
+                (Ok(bytes_read as usize), buf)
+            }
+            Err(err) => (Err(err), self.buf),
+        }
+    }
+}
+
+/// Send from a registered buffer.
+///
+/// Sockets do not have a file position, so this is issued as a
+/// `WriteFixed` with the offset ignored by the kernel.
+#[derive(Debug)]
+pub struct SendFixed {
+    fd: NornFd,
+    buf: FixedBuf,
+}
+
+impl SendFixed {
+    pub(crate) fn new(fd: NornFd, buf: FixedBuf) -> Self {
+        Self { fd, buf }
+    }
+}
+
+impl Operation for SendFixed {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let ptr = self.buf.stable_ptr();
+        let len = self.buf.bytes_init();
+        let index = self.buf.buf_index();
+        match self.fd.kind() {
+            crate::fd::FdKind::Fd(fd) => opcode::WriteFixed::new(*fd, ptr, len as _, index).build(),
+            crate::fd::FdKind::Fixed(fd) => {
+                opcode::WriteFixed::new(*fd, ptr, len as _, index).build()
+            }
+        }
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl Singleshot for SendFixed {
+    type Output = (io::Result<usize>, FixedBuf);
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        (result.result.map(|v| v as usize), self.buf)
+    }
+}
+
 pub struct Poll<const MULTI: bool> {
     fd: NornFd,
     events: u32,
@@ -1,16 +1,27 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
 use std::io;
 use std::mem::ManuallyDrop;
 use std::net::SocketAddr;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, RawFd};
 use std::pin::Pin;
-use std::task::{ready, Context, Poll};
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+use std::time::Duration;
 
 use futures_core::Stream;
 use socket2::{Domain, Type};
 
-use crate::buf::{StableBuf, StableBufMut};
-use crate::bufring::BufRing;
-use crate::net::socket;
+use crate::buf::{StableBuf, StableBufMut, StableBufs};
+use crate::bufring::{BufRing, BufRingBuf};
+use crate::fd::NornFd;
+use crate::fixedbuf::FixedBuf;
+use crate::net::cmsg::ControlMessageBuf;
+use crate::net::completion::{CompletionReader, CompletionWriter};
+use crate::net::{connect, socket};
 use crate::operation::Op;
+use crate::pipe::{PipeReader, PipeWriter};
+use crate::time;
 
 use super::socket::Accept;
 
@@ -23,6 +34,166 @@ pub struct TcpListener {
     socket: socket::Socket,
 }
 
+/// A builder for TCP sockets, for setting options which must be applied
+/// before the socket is bound or connected.
+///
+/// ```no_run
+/// # async fn example() -> std::io::Result<()> {
+/// use norn_uring::net::TcpSocketBuilder;
+///
+/// // Share a port between multiple listeners, e.g. one per core.
+/// let listener = TcpSocketBuilder::new()
+///     .local_addr("0.0.0.0:8080".parse().unwrap())
+///     .reuse_port(true)
+///     .listen(1024)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TcpSocketBuilder {
+    options: socket::SocketOptions,
+    timeout: Option<Duration>,
+    attempt_timeout: Option<Duration>,
+    attempt_delay: Duration,
+}
+
+impl Default for TcpSocketBuilder {
+    fn default() -> Self {
+        Self {
+            options: socket::SocketOptions::default(),
+            timeout: None,
+            attempt_timeout: None,
+            attempt_delay: connect::DEFAULT_ATTEMPT_DELAY,
+        }
+    }
+}
+
+impl TcpSocketBuilder {
+    /// Creates a new builder with no options set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum time to wait for a connection to be established.
+    ///
+    /// This bounds the whole of [`TcpSocketBuilder::connect`] or
+    /// [`TcpSocketBuilder::connect_any`], which fail with
+    /// [`io::ErrorKind::TimedOut`] once it elapses. By default there is no limit.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum time to wait for each connection attempt made by
+    /// [`TcpSocketBuilder::connect_any`].
+    ///
+    /// An attempt which times out fails, and the next address is tried.
+    pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the delay between starting connection attempts in
+    /// [`TcpSocketBuilder::connect_any`].
+    ///
+    /// Defaults to 250ms, as recommended by RFC 8305.
+    pub fn attempt_delay(mut self, delay: Duration) -> Self {
+        self.attempt_delay = delay;
+        self
+    }
+
+    /// Set the local address to bind the socket to.
+    ///
+    /// This is required for [`TcpSocketBuilder::listen`]. If not set for
+    /// [`TcpSocketBuilder::connect`], the kernel picks the local address.
+    pub fn local_addr(mut self, addr: SocketAddr) -> Self {
+        self.options.local_addr = Some(addr);
+        self
+    }
+
+    /// Set value for the SO_REUSEADDR option on the socket.
+    pub fn reuse_address(mut self, reuse: bool) -> Self {
+        self.options.reuse_address = Some(reuse);
+        self
+    }
+
+    /// Set value for the SO_REUSEPORT option on the socket.
+    ///
+    /// This allows multiple listeners to bind to the same address, with the
+    /// kernel distributing incoming connections between them.
+    pub fn reuse_port(mut self, reuse: bool) -> Self {
+        self.options.reuse_port = Some(reuse);
+        self
+    }
+
+    /// Set value for the IPV6_V6ONLY option on the socket.
+    pub fn only_v6(mut self, only_v6: bool) -> Self {
+        self.options.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Bind the socket to the given network interface with SO_BINDTODEVICE.
+    pub fn bind_device(mut self, interface: impl AsRef<[u8]>) -> Self {
+        self.options.device = Some(interface.as_ref().to_vec());
+        self
+    }
+
+    /// Set value for the SO_RCVBUF option on the socket.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.options.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set value for the SO_SNDBUF option on the socket.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.options.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Creates a TCP listener bound to the local address.
+    pub async fn listen(self, backlog: u32) -> io::Result<TcpListener> {
+        let socket = self.options.open(Type::STREAM, None).await?;
+        socket.listen(backlog)?;
+        Ok(TcpListener { socket })
+    }
+
+    /// Creates a TCP connection to the specified address.
+    pub async fn connect(self, addr: SocketAddr) -> io::Result<TcpSocket> {
+        self.connect_any([addr]).await
+    }
+
+    /// Creates a TCP connection to one of the specified addresses.
+    ///
+    /// Connection attempts are raced as described by Happy Eyeballs ([RFC 8305]).
+    /// The addresses are interleaved by address family, starting with the family
+    /// of the first address. A new attempt is started whenever the previous one
+    /// fails, or after the [attempt delay](TcpSocketBuilder::attempt_delay) if it
+    /// has not completed. The first connection established is returned and all
+    /// other attempts are cancelled.
+    ///
+    /// If every attempt fails, the error of the last attempt to fail is returned.
+    ///
+    /// [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305
+    pub async fn connect_any(
+        self,
+        addrs: impl IntoIterator<Item = SocketAddr>,
+    ) -> io::Result<TcpSocket> {
+        let addrs = addrs.into_iter().collect();
+        let connect = connect::connect_any(
+            &self.options,
+            addrs,
+            self.attempt_delay,
+            self.attempt_timeout,
+        );
+        let socket = match self.timeout {
+            Some(timeout) => time::timeout(timeout, connect).await??,
+            None => connect.await?,
+        };
+        Ok(TcpSocket { socket })
+    }
+}
+
 /// A TCP socket.
 ///
 /// [`TcpSocket`] provides a low-level interface for configuring a socket
@@ -35,17 +206,13 @@ pub struct TcpSocket {
     socket: socket::Socket,
 }
 
-pin_project_lite::pin_project! {
-    /// [`TcpStream`] represents a connected TCP socket.
-    ///
-    /// Bytes can be read from and written to the socket using the
-    /// [`AsyncRead`](tokio::io::AsyncRead) and [`AsyncWrite`](tokio::io::AsyncWrite) traits.
-    pub struct TcpStream {
-        #[pin]
-        reader: TcpStreamReader,
-        #[pin]
-        writer: TcpStreamWriter,
-    }
+/// [`TcpStream`] represents a connected TCP socket.
+///
+/// Bytes can be read from and written to the socket using the
+/// [`AsyncRead`](tokio::io::AsyncRead) and [`AsyncWrite`](tokio::io::AsyncWrite) traits.
+pub struct TcpStream {
+    reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
 }
 
 impl std::fmt::Debug for TcpListener {
@@ -60,6 +227,76 @@ impl std::fmt::Debug for TcpSocket {
     }
 }
 
+impl AsRawFd for TcpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl AsFd for TcpListener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // Safety: the file descriptor is open for as long as the listener.
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+impl FromRawFd for TcpListener {
+    /// Adopts a listening socket.
+    ///
+    /// The file descriptor must be in non-blocking mode, and this panics if called
+    /// outside of a driver context.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        let socket = socket::Socket::from_raw_fd(fd);
+        TcpListener { socket }
+    }
+}
+
+impl IntoRawFd for TcpListener {
+    /// See [`TcpListener::into_std`].
+    ///
+    /// This panics if the listener is still in use by an operation.
+    fn into_raw_fd(self) -> RawFd {
+        self.into_std()
+            .expect("listener is still in use by an operation")
+            .into_raw_fd()
+    }
+}
+
+impl AsRawFd for TcpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl AsFd for TcpSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // Safety: the file descriptor is open for as long as the socket.
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+impl FromRawFd for TcpSocket {
+    /// Adopts a connected socket.
+    ///
+    /// The file descriptor must be in non-blocking mode, and this panics if called
+    /// outside of a driver context.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        let socket = socket::Socket::from_raw_fd(fd);
+        TcpSocket { socket }
+    }
+}
+
+impl IntoRawFd for TcpSocket {
+    /// See [`TcpSocket::into_std`].
+    ///
+    /// This panics if the socket is still in use by an operation.
+    fn into_raw_fd(self) -> RawFd {
+        self.into_std()
+            .expect("socket is still in use by an operation")
+            .into_raw_fd()
+    }
+}
+
 impl std::fmt::Debug for TcpStream {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("TcpStream").finish()
@@ -74,7 +311,35 @@ impl TcpListener {
         Ok(TcpListener { socket: inner })
     }
 
+    /// Creates a listener from a bound and listening standard library listener.
+    ///
+    /// The socket is put in non-blocking mode. This can be used to adopt a socket
+    /// inherited from another process.
+    ///
+    /// This panics if called outside of a driver context.
+    pub fn from_std(listener: std::net::TcpListener) -> io::Result<TcpListener> {
+        let socket = socket::Socket::from_socket2(listener.into())?;
+        Ok(TcpListener { socket })
+    }
+
+    /// Converts the listener into a standard library listener.
+    ///
+    /// The returned listener is in non-blocking mode. Ownership of the file
+    /// descriptor moves to the returned listener, so the conversion fails,
+    /// returning `self`, while an operation on the listener is still pending.
+    /// This includes the accept of a dropped [`Incoming`] stream which is still
+    /// being cancelled.
+    pub fn into_std(self) -> Result<std::net::TcpListener, TcpListener> {
+        match self.socket.into_socket2() {
+            Ok(socket) => Ok(socket.into()),
+            Err(socket) => Err(TcpListener { socket }),
+        }
+    }
+
     /// Set value for the SO_REUSEADDR option on this socket.
+    ///
+    /// This has no effect on the address the listener is already bound to, use
+    /// [`TcpSocketBuilder::reuse_address`] to set it before binding.
     pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
         self.socket.as_socket().set_reuse_address(reuse)
     }
@@ -98,12 +363,262 @@ impl TcpListener {
         }
     }
 
+    /// Returns a stream of incoming connections which enforces the limits set
+    /// in `options`.
+    ///
+    /// While the maximum number of connections is reached the multishot accept
+    /// is cancelled, leaving new connections in the listen backlog. It is re-armed
+    /// once a [`ConnectionPermit`] is dropped. Connections the kernel accepted
+    /// before the cancellation took effect are queued and handed out first.
+    pub fn incoming_bounded(&self, options: AcceptOptions) -> BoundedIncoming<'_> {
+        BoundedIncoming {
+            listener: &self.socket,
+            options,
+            limiter: Rc::new(Limiter {
+                active: Cell::new(0),
+                waker: Cell::new(None),
+            }),
+            queued: VecDeque::new(),
+            queued_total: 0,
+            pauses: 0,
+            cancelling: false,
+            current: None,
+        }
+    }
+
     /// Closes the listener.
     pub async fn close(self) -> io::Result<()> {
         self.socket.close().await
     }
 }
 
+/// Options for [`TcpListener::incoming_bounded`].
+#[derive(Debug, Clone)]
+pub struct AcceptOptions {
+    max_connections: usize,
+    nodelay: Option<bool>,
+    keepalive: Option<bool>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+}
+
+impl AcceptOptions {
+    /// Creates options allowing at most `max_connections` accepted connections
+    /// to be open at once.
+    pub fn new(max_connections: usize) -> Self {
+        Self {
+            max_connections,
+            nodelay: None,
+            keepalive: None,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+        }
+    }
+
+    /// Set the TCP_NODELAY option on each accepted socket.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Set the SO_KEEPALIVE option on each accepted socket.
+    pub fn keepalive(mut self, keepalive: bool) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Set the SO_RCVBUF option on each accepted socket.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the SO_SNDBUF option on each accepted socket.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    fn apply(&self, socket: &TcpSocket) -> io::Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            socket.set_nodelay(nodelay)?;
+        }
+        if let Some(keepalive) = self.keepalive {
+            socket.set_keepalive(keepalive)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        Ok(())
+    }
+}
+
+// `Limiter` is shared between a `BoundedIncoming` and the permits it has handed out.
+struct Limiter {
+    active: Cell<usize>,
+    // `waker` is set while the stream is paused, and woken when a permit is released.
+    waker: Cell<Option<Waker>>,
+}
+
+/// A permit for a connection accepted by [`BoundedIncoming`].
+///
+/// The connection counts towards the limit until the permit is dropped.
+pub struct ConnectionPermit {
+    limiter: Rc<Limiter>,
+}
+
+impl std::fmt::Debug for ConnectionPermit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionPermit").finish()
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.active.set(self.limiter.active.get() - 1);
+        if let Some(waker) = self.limiter.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A stream of incoming connections with a limit on how many may be open at once.
+    ///
+    /// This is returned by [`TcpListener::incoming_bounded`]. Each connection is
+    /// paired with a [`ConnectionPermit`] which must be kept alive for as long as
+    /// the connection.
+    pub struct BoundedIncoming<'a> {
+        listener: &'a socket::Socket,
+        options: AcceptOptions,
+        limiter: Rc<Limiter>,
+        // Connections accepted by the kernel which are waiting for a permit.
+        queued: VecDeque<NornFd>,
+        // Cumulative counters, see `queued_total` and `pauses`.
+        queued_total: u64,
+        pauses: u64,
+        // Set once cancellation of `current` was requested.
+        cancelling: bool,
+        #[pin]
+        current: Option<Op<Accept<true>>>,
+    }
+}
+
+impl BoundedIncoming<'_> {
+    /// Returns the number of accepted connections whose permits are still held.
+    pub fn active(&self) -> usize {
+        self.limiter.active.get()
+    }
+
+    /// Returns the number of connections accepted by the kernel while the limit
+    /// was reached, which are handed out once permits are released.
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Returns the total number of connections which were queued because they
+    /// arrived while the limit was reached.
+    pub fn queued_total(&self) -> u64 {
+        self.queued_total
+    }
+
+    /// Returns the number of times accepting was paused because the limit was
+    /// reached.
+    pub fn pauses(&self) -> u64 {
+        self.pauses
+    }
+}
+
+impl Stream for BoundedIncoming<'_> {
+    type Item = io::Result<(TcpSocket, ConnectionPermit)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if this.limiter.active.get() >= this.options.max_connections {
+                if !*this.cancelling {
+                    if let Some(current) = this.current.as_mut().as_pin_mut() {
+                        log::trace!(target: LOG, "incoming.pause");
+                        *this.pauses += 1;
+                        *this.cancelling = current.cancel();
+                        if !*this.cancelling {
+                            this.current.set(None);
+                        }
+                    }
+                }
+                // Queue the connections accepted before the cancellation takes effect.
+                if let Some(mut current) = this.current.as_mut().as_pin_mut() {
+                    while let Poll::Ready(item) = current.as_mut().poll_next(cx) {
+                        match item {
+                            Some(Ok(fd)) => {
+                                this.queued.push_back(fd);
+                                *this.queued_total += 1;
+                            }
+                            Some(Err(err)) if err.raw_os_error() == Some(libc::ECANCELED) => {}
+                            Some(Err(err)) => {
+                                log::debug!(target: LOG, "incoming.cancel_failed {err}");
+                            }
+                            None => {
+                                *this.cancelling = false;
+                                this.current.set(None);
+                                break;
+                            }
+                        }
+                    }
+                }
+                this.limiter.waker.set(Some(cx.waker().clone()));
+                return Poll::Pending;
+            }
+
+            let fd = if let Some(fd) = this.queued.pop_front() {
+                fd
+            } else if let Some(current) = this.current.as_mut().as_pin_mut() {
+                match ready!(current.poll_next(cx)) {
+                    Some(Ok(fd)) => fd,
+                    Some(Err(err)) if *this.cancelling => {
+                        log::trace!(target: LOG, "incoming.cancelled {err}");
+                        continue;
+                    }
+                    Some(Err(err)) => {
+                        this.current.set(None);
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    None if *this.cancelling => {
+                        // Resume accepting after a pause.
+                        *this.cancelling = false;
+                        this.current.set(None);
+                        continue;
+                    }
+                    None => {
+                        this.current.set(None);
+                        return Poll::Ready(None);
+                    }
+                }
+            } else {
+                log::trace!(target: LOG, "incoming.arm");
+                this.current.set(Some(this.listener.accept_multi()));
+                continue;
+            };
+
+            let socket = TcpSocket {
+                socket: socket::Socket::from_fd(fd),
+            };
+            // The connection is still handed out if its options could not be set.
+            if let Err(err) = this.options.apply(&socket) {
+                log::warn!(target: LOG, "incoming.options_failed {err}");
+            }
+            this.limiter.active.set(this.limiter.active.get() + 1);
+            let permit = ConnectionPermit {
+                limiter: this.limiter.clone(),
+            };
+            return Poll::Ready(Some(Ok((socket, permit))));
+        }
+    }
+}
+
 pin_project_lite::pin_project! {
     pub struct Incoming<'a> {
         listener: &'a socket::Socket,
@@ -112,6 +627,25 @@ pin_project_lite::pin_project! {
     }
 }
 
+pin_project_lite::pin_project! {
+    /// A stream of buffers received on a [`TcpSocket`].
+    ///
+    /// This is returned by [`TcpSocket::recv_multi`].
+    #[derive(Debug)]
+    pub struct RecvMultiStream<'a> {
+        #[pin]
+        inner: socket::RingStream<'a, socket::RecvMulti>,
+    }
+}
+
+impl Stream for RecvMultiStream<'_> {
+    type Item = io::Result<BufRingBuf>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_next(cx)
+    }
+}
+
 impl Stream for Incoming<'_> {
     type Item = io::Result<TcpSocket>;
 
@@ -155,6 +689,39 @@ impl TcpSocket {
         Ok(TcpSocket { socket })
     }
 
+    /// Creates a TCP connection to one of the specified addresses.
+    ///
+    /// This uses the defaults of [`TcpSocketBuilder::connect_any`], use
+    /// [`TcpSocketBuilder`] to set timeouts.
+    pub async fn connect_any(addrs: impl IntoIterator<Item = SocketAddr>) -> io::Result<TcpSocket> {
+        TcpSocketBuilder::new().connect_any(addrs).await
+    }
+
+    /// Creates a socket from a connected standard library stream.
+    ///
+    /// The socket is put in non-blocking mode. This can be used to adopt a socket
+    /// inherited from another process.
+    ///
+    /// This panics if called outside of a driver context.
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<TcpSocket> {
+        let socket = socket::Socket::from_socket2(stream.into())?;
+        Ok(TcpSocket { socket })
+    }
+
+    /// Converts the socket into a standard library stream.
+    ///
+    /// The returned stream is in non-blocking mode. Ownership of the file
+    /// descriptor moves to the returned stream, so the conversion fails,
+    /// returning `self`, while an operation on the socket is still pending.
+    /// This includes operation futures which have not been polled or dropped
+    /// yet, and dropped operations which are still being cancelled.
+    pub fn into_std(self) -> Result<std::net::TcpStream, TcpSocket> {
+        match self.socket.into_socket2() {
+            Ok(socket) => Ok(socket.into()),
+            Err(socket) => Err(TcpSocket { socket }),
+        }
+    }
+
     /// Returns the local address that this stream is bound to.
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
         self.socket.local_addr()
@@ -186,6 +753,68 @@ impl TcpSocket {
         self.socket.send(buf)
     }
 
+    /// Send data from the given buffer without copying it into the kernel.
+    ///
+    /// This is equivalent to [`TcpSocket::send`], but the kernel transmits
+    /// directly from the buffer. The operation completes, returning the
+    /// buffer, only once the kernel no longer references it. Zero-copy sends
+    /// are most useful for large payloads, for small buffers the cost of
+    /// pinning the pages outweighs the copy.
+    pub fn send_zc<B: StableBuf>(&self, buf: B) -> Op<socket::SendZc<B>> {
+        self.socket.send_zc(buf, None)
+    }
+
+    /// Send data gathered from the given buffers, along with the given control
+    /// messages, without copying the buffers into the kernel.
+    ///
+    /// This is equivalent to [`TcpSocket::send_zc`], but sends a single
+    /// message with `sendmsg`. The buffers are returned once the kernel no
+    /// longer references them.
+    pub async fn send_msg_zc<V: StableBufs>(
+        &self,
+        bufs: V,
+        control: &ControlMessageBuf,
+    ) -> (io::Result<usize>, V) {
+        self.socket.send_msg_zc(bufs, None, control).await
+    }
+
+    /// Recv data into the given registered buffer.
+    ///
+    /// This is equivalent to [`TcpSocket::recv`], but uses a buffer from a
+    /// [`FixedBufPool`](crate::fixedbuf::FixedBufPool).
+    pub fn recv_fixed(&self, buf: FixedBuf) -> Op<socket::RecvFixed> {
+        self.socket.recv_fixed(buf)
+    }
+
+    /// Send data from the given registered buffer.
+    ///
+    /// This is equivalent to [`TcpSocket::send`], but uses a buffer from a
+    /// [`FixedBufPool`](crate::fixedbuf::FixedBufPool).
+    pub fn send_fixed(&self, buf: FixedBuf) -> Op<socket::SendFixed> {
+        self.socket.send_fixed(buf)
+    }
+
+    /// Splice up to `len` bytes received on the socket into a pipe.
+    ///
+    /// Waits for the socket to become readable if no data is available.
+    /// Returns the number of bytes moved, 0 indicates that the peer has shut
+    /// down the connection.
+    pub async fn splice_to(&self, pipe: &PipeWriter, len: u32) -> io::Result<usize> {
+        self.socket.splice_to(pipe, len).await
+    }
+
+    /// Splice up to `len` bytes from a pipe into the socket.
+    ///
+    /// Waits for the socket to become writable if its send buffer is full.
+    /// Returns the number of bytes moved.
+    pub async fn splice_from(&self, pipe: &PipeReader, len: u32) -> io::Result<usize> {
+        self.socket.splice_from(pipe, len).await
+    }
+
+    pub(crate) fn socket(&self) -> &socket::Socket {
+        &self.socket
+    }
+
     /// Recv data using the given buffer ring.
     ///
     /// A buffer will be taken from the ring when the operation is completed
@@ -195,16 +824,65 @@ impl TcpSocket {
         self.socket.recv_from_ring(ring)
     }
 
+    /// Returns a stream of buffers received on the socket, using buffers
+    /// from the given buffer ring.
+    ///
+    /// A single multishot receive is kept armed for the lifetime of the
+    /// stream. If the kernel stops the receive, for example because the ring
+    /// ran out of buffers, it is transparently re-armed once a buffer has
+    /// been returned to the ring. The stream ends once the peer shuts down
+    /// the connection.
+    pub fn recv_multi<'a>(&'a self, ring: &'a BufRing) -> RecvMultiStream<'a> {
+        RecvMultiStream {
+            inner: self.socket.recv_multi(ring),
+        }
+    }
+
     /// Convert this socket into a stream.
     ///
     /// [`TcpStream`] is a stateful wrapper around [`TcpSocket`] that implements
     /// [`AsyncRead`](tokio::io::AsyncRead) and [`AsyncWrite`](tokio::io::AsyncWrite).
     pub fn into_stream(self) -> TcpStream {
-        let reader = TcpStreamReader {
-            inner: ReadyStream::new(self.socket.clone()),
+        let reader = OwnedReadHalf {
+            socket: self.socket.clone(),
+            inner: Box::pin(ReaderInner::Ready {
+                inner: ReadyStream::new(self.socket.clone()),
+            }),
+        };
+        let writer = OwnedWriteHalf {
+            socket: self.socket.clone(),
+            inner: Box::pin(WriterInner::Ready {
+                inner: ReadyStream::new(self.socket.clone()),
+            }),
+        };
+
+        TcpStream { reader, writer }
+    }
+
+    /// Convert this socket into a completion based stream.
+    ///
+    /// Unlike [`TcpSocket::into_stream`], which waits for readiness and then
+    /// performs the read or write on the socket, the returned stream submits
+    /// the I/O itself to io_uring. Reads are served from a multishot receive
+    /// into buffers selected from `ring`, and writes are copied into owned
+    /// buffers which are sent asynchronously. Written data is only guaranteed
+    /// to have been sent once the stream is flushed.
+    ///
+    /// The ring may be shared between streams, but each buffer is held by a
+    /// stream until it has been read, so it should be sized for the number of
+    /// connections.
+    pub fn into_completion_stream(self, ring: &BufRing) -> TcpStream {
+        let reader = OwnedReadHalf {
+            socket: self.socket.clone(),
+            inner: Box::pin(ReaderInner::Completion {
+                inner: CompletionReader::new(&self.socket, ring),
+            }),
         };
-        let writer = TcpStreamWriter {
-            inner: ReadyStream::new(self.socket.clone()),
+        let writer = OwnedWriteHalf {
+            socket: self.socket.clone(),
+            inner: Box::pin(WriterInner::Completion {
+                inner: CompletionWriter::new(self.socket.clone()),
+            }),
         };
 
         TcpStream { reader, writer }
@@ -237,13 +915,481 @@ impl TcpSocket {
     pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
         self.socket.as_socket().set_nodelay(nodelay)
     }
+
+    /// Get the value of the SO_RCVBUF option on this socket.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.socket.as_socket().recv_buffer_size()
+    }
+
+    /// Get the value of the SO_SNDBUF option on this socket.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.socket.as_socket().send_buffer_size()
+    }
+
+    /// Get the value of the SO_KEEPALIVE option on this socket.
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.socket.as_socket().keepalive()
+    }
+
+    /// Get the value of the TCP_NODELAY option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        self.socket.as_socket().nodelay()
+    }
+
+    /// Get the value of the TCP_KEEPIDLE option on this socket.
+    ///
+    /// This is the time the connection must be idle before keepalive probes are sent.
+    pub fn keepalive_time(&self) -> io::Result<Duration> {
+        self.socket.as_socket().keepalive_time()
+    }
+
+    /// Set the value of the TCP_KEEPIDLE option on this socket.
+    ///
+    /// This also enables SO_KEEPALIVE.
+    pub fn set_keepalive_time(&self, time: Duration) -> io::Result<()> {
+        let keepalive = socket2::TcpKeepalive::new().with_time(time);
+        self.socket.as_socket().set_tcp_keepalive(&keepalive)
+    }
+
+    /// Get the value of the TCP_KEEPINTVL option on this socket.
+    ///
+    /// This is the time between keepalive probes.
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.socket.as_socket().keepalive_interval()
+    }
+
+    /// Set the value of the TCP_KEEPINTVL option on this socket.
+    ///
+    /// This also enables SO_KEEPALIVE.
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        let keepalive = socket2::TcpKeepalive::new().with_interval(interval);
+        self.socket.as_socket().set_tcp_keepalive(&keepalive)
+    }
+
+    /// Get the value of the TCP_KEEPCNT option on this socket.
+    ///
+    /// This is the number of unanswered probes before the connection is dropped.
+    pub fn keepalive_retries(&self) -> io::Result<u32> {
+        self.socket.as_socket().keepalive_retries()
+    }
+
+    /// Set the value of the TCP_KEEPCNT option on this socket.
+    ///
+    /// This also enables SO_KEEPALIVE.
+    pub fn set_keepalive_retries(&self, retries: u32) -> io::Result<()> {
+        let keepalive = socket2::TcpKeepalive::new().with_retries(retries);
+        self.socket.as_socket().set_tcp_keepalive(&keepalive)
+    }
+
+    /// Get the value of the TCP_USER_TIMEOUT option on this socket.
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.as_socket().tcp_user_timeout()
+    }
+
+    /// Set the value of the TCP_USER_TIMEOUT option on this socket.
+    ///
+    /// This is the maximum time transmitted data may remain unacknowledged
+    /// before the connection is closed. `None` uses the system default.
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.as_socket().set_tcp_user_timeout(timeout)
+    }
+
+    /// Get the value of the SO_LINGER option on this socket.
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        self.socket.as_socket().linger()
+    }
+
+    /// Set the value of the SO_LINGER option on this socket.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.socket.as_socket().set_linger(linger)
+    }
+
+    /// Get the value of the TCP_QUICKACK option on this socket.
+    pub fn quickack(&self) -> io::Result<bool> {
+        self.socket.as_socket().quickack()
+    }
+
+    /// Set the value of the TCP_QUICKACK option on this socket.
+    ///
+    /// The kernel may reset this option, so it is not permanent.
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        self.socket.as_socket().set_quickack(quickack)
+    }
+
+    /// Get the value of the TCP_CORK option on this socket.
+    pub fn cork(&self) -> io::Result<bool> {
+        self.socket.as_socket().cork()
+    }
+
+    /// Set the value of the TCP_CORK option on this socket.
+    ///
+    /// While corked, partial frames are not sent.
+    pub fn set_cork(&self, cork: bool) -> io::Result<()> {
+        self.socket.as_socket().set_cork(cork)
+    }
+
+    /// Get the value of the TCP_NOTSENT_LOWAT option on this socket.
+    pub fn notsent_lowat(&self) -> io::Result<u32> {
+        self.socket
+            .get_option::<u32>(libc::IPPROTO_TCP, libc::TCP_NOTSENT_LOWAT)
+    }
+
+    /// Set the value of the TCP_NOTSENT_LOWAT option on this socket.
+    ///
+    /// This limits the amount of unsent data in the send buffer before the
+    /// socket stops reporting itself as writable.
+    pub fn set_notsent_lowat(&self, lowat: u32) -> io::Result<()> {
+        self.socket
+            .set_option(libc::IPPROTO_TCP, libc::TCP_NOTSENT_LOWAT, &lowat)
+    }
+
+    /// Get the value of the TCP_CONGESTION option on this socket.
+    pub fn congestion(&self) -> io::Result<Vec<u8>> {
+        let mut name = self.socket.as_socket().tcp_congestion()?;
+        // The kernel pads the name with NUL bytes.
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        name.truncate(len);
+        Ok(name)
+    }
+
+    /// Set the value of the TCP_CONGESTION option on this socket, e.g. `b"cubic"`.
+    pub fn set_congestion(&self, algorithm: &[u8]) -> io::Result<()> {
+        self.socket.as_socket().set_tcp_congestion(algorithm)
+    }
+
+    /// Get the value of the IP_TOS option on this socket.
+    pub fn tos(&self) -> io::Result<u32> {
+        self.socket.as_socket().tos()
+    }
+
+    /// Set the value of the IP_TOS option on this socket.
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.socket.as_socket().set_tos(tos)
+    }
+
+    /// Get the value of the SO_MARK option on this socket.
+    pub fn mark(&self) -> io::Result<u32> {
+        self.socket.as_socket().mark()
+    }
+
+    /// Set the value of the SO_MARK option on this socket.
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    pub fn set_mark(&self, mark: u32) -> io::Result<()> {
+        self.socket.as_socket().set_mark(mark)
+    }
+
+    /// Get and clear the value of the SO_ERROR option on this socket.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.socket.as_socket().take_error()
+    }
+
+    /// Returns a snapshot of the TCP_INFO statistics for this socket.
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        let info = self
+            .socket
+            .get_option::<libc::tcp_info>(libc::IPPROTO_TCP, libc::TCP_INFO)?;
+        Ok(TcpInfo { info })
+    }
+}
+
+/// A snapshot of the kernel's statistics for a TCP connection.
+///
+/// This is returned by [`TcpSocket::tcp_info`].
+#[derive(Clone, Copy)]
+pub struct TcpInfo {
+    info: libc::tcp_info,
+}
+
+impl TcpInfo {
+    /// Returns the connection state, one of the `TCP_*` states such as
+    /// `TCP_ESTABLISHED` (1).
+    pub fn state(&self) -> u8 {
+        self.info.tcpi_state
+    }
+
+    /// Returns the smoothed round trip time.
+    pub fn rtt(&self) -> Duration {
+        Duration::from_micros(self.info.tcpi_rtt.into())
+    }
+
+    /// Returns the round trip time variance.
+    pub fn rtt_var(&self) -> Duration {
+        Duration::from_micros(self.info.tcpi_rttvar.into())
+    }
+
+    /// Returns the retransmission timeout.
+    pub fn rto(&self) -> Duration {
+        Duration::from_micros(self.info.tcpi_rto.into())
+    }
+
+    /// Returns the sender's maximum segment size.
+    pub fn snd_mss(&self) -> u32 {
+        self.info.tcpi_snd_mss
+    }
+
+    /// Returns the receiver's maximum segment size.
+    pub fn rcv_mss(&self) -> u32 {
+        self.info.tcpi_rcv_mss
+    }
+
+    /// Returns the congestion window, in segments.
+    pub fn snd_cwnd(&self) -> u32 {
+        self.info.tcpi_snd_cwnd
+    }
+
+    /// Returns the slow start threshold, in segments.
+    pub fn snd_ssthresh(&self) -> u32 {
+        self.info.tcpi_snd_ssthresh
+    }
+
+    /// Returns the number of segments sent but not yet acknowledged.
+    pub fn unacked(&self) -> u32 {
+        self.info.tcpi_unacked
+    }
+
+    /// Returns the number of segments considered lost.
+    pub fn lost(&self) -> u32 {
+        self.info.tcpi_lost
+    }
+
+    /// Returns the total number of retransmitted segments.
+    pub fn total_retransmits(&self) -> u32 {
+        self.info.tcpi_total_retrans
+    }
+
+    /// Returns the path MTU.
+    pub fn pmtu(&self) -> u32 {
+        self.info.tcpi_pmtu
+    }
+
+    /// Returns the receive window the socket is advertising space for.
+    pub fn rcv_space(&self) -> u32 {
+        self.info.tcpi_rcv_space
+    }
+}
+
+impl std::fmt::Debug for TcpInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TcpInfo")
+            .field("state", &self.state())
+            .field("rtt", &self.rtt())
+            .field("rtt_var", &self.rtt_var())
+            .field("rto", &self.rto())
+            .field("snd_mss", &self.snd_mss())
+            .field("rcv_mss", &self.rcv_mss())
+            .field("snd_cwnd", &self.snd_cwnd())
+            .field("snd_ssthresh", &self.snd_ssthresh())
+            .field("unacked", &self.unacked())
+            .field("lost", &self.lost())
+            .field("total_retransmits", &self.total_retransmits())
+            .field("pmtu", &self.pmtu())
+            .field("rcv_space", &self.rcv_space())
+            .finish()
+    }
 }
 
 impl TcpStream {
     /// Split the stream into a reader and writer.
-    pub fn owned_split(self) -> (TcpStreamReader, TcpStreamWriter) {
+    ///
+    /// The halves can be rejoined with [`OwnedReadHalf::reunite`].
+    pub fn owned_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
         (self.reader, self.writer)
     }
+
+    /// Split the stream into a reader and writer which borrow it.
+    pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        (
+            ReadHalf {
+                inner: &mut self.reader,
+            },
+            WriteHalf {
+                inner: &mut self.writer,
+            },
+        )
+    }
+
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.reader.local_addr()
+    }
+
+    /// Returns the remote address that this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.reader.peer_addr()
+    }
+}
+
+/// The read half of a [`TcpStream`] created by [`TcpStream::split`].
+pub struct ReadHalf<'a> {
+    inner: &'a mut OwnedReadHalf,
+}
+
+/// The write half of a [`TcpStream`] created by [`TcpStream::split`].
+pub struct WriteHalf<'a> {
+    inner: &'a mut OwnedWriteHalf,
+}
+
+impl std::fmt::Debug for ReadHalf<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadHalf").finish()
+    }
+}
+
+impl std::fmt::Debug for WriteHalf<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteHalf").finish()
+    }
+}
+
+impl ReadHalf<'_> {
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Returns the remote address that this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of the connection.
+    pub async fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how).await
+    }
+}
+
+impl WriteHalf<'_> {
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Returns the remote address that this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of the connection.
+    pub async fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how).await
+    }
+}
+
+impl tokio::io::AsyncRead for ReadHalf<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.inner).poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncWrite for WriteHalf<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut *self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut *self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut *self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+/// Error returned by [`OwnedReadHalf::reunite`] and [`OwnedWriteHalf::reunite`]
+/// when the halves are not from the same stream.
+///
+/// The halves are returned unchanged.
+#[derive(Debug, thiserror::Error)]
+#[error("tried to reunite halves that are not from the same stream")]
+pub struct ReuniteError(pub OwnedReadHalf, pub OwnedWriteHalf);
+
+impl OwnedReadHalf {
+    /// Rejoin this half with the write half it was split from.
+    // The error hands both halves back to the caller, so it can't be made smaller.
+    #[allow(clippy::result_large_err)]
+    pub fn reunite(self, other: OwnedWriteHalf) -> Result<TcpStream, ReuniteError> {
+        if !self.socket.fd().same_fd(other.socket.fd()) {
+            return Err(ReuniteError(self, other));
+        }
+        Ok(TcpStream {
+            reader: self,
+            writer: other,
+        })
+    }
+
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Returns the remote address that this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of the connection.
+    pub async fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
+        self.socket.shutdown(how).await
+    }
+}
+
+impl OwnedWriteHalf {
+    /// Rejoin this half with the read half it was split from.
+    #[allow(clippy::result_large_err)]
+    pub fn reunite(self, other: OwnedReadHalf) -> Result<TcpStream, ReuniteError> {
+        other.reunite(self)
+    }
+
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Returns the remote address that this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of the connection.
+    pub async fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
+        self.socket.shutdown(how).await
+    }
+}
+
+impl std::fmt::Debug for OwnedReadHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedReadHalf").finish()
+    }
+}
+
+impl std::fmt::Debug for OwnedWriteHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedWriteHalf").finish()
+    }
 }
 
 impl tokio::io::AsyncRead for TcpStream {
@@ -252,8 +1398,7 @@ impl tokio::io::AsyncRead for TcpStream {
         cx: &mut Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        let this = self.project();
-        this.reader.poll_read(cx, buf)
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
     }
 }
 
@@ -263,18 +1408,15 @@ impl tokio::io::AsyncWrite for TcpStream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        let this = self.project();
-        this.writer.poll_write(cx, buf)
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        let this = self.project();
-        this.writer.poll_flush(cx)
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
     }
 
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        let this = self.project();
-        this.writer.poll_shutdown(cx)
+        Pin::new(&mut self.get_mut().writer).poll_shutdown(cx)
     }
 
     fn poll_write_vectored(
@@ -282,8 +1424,7 @@ impl tokio::io::AsyncWrite for TcpStream {
         cx: &mut Context<'_>,
         bufs: &[io::IoSlice<'_>],
     ) -> Poll<Result<usize, io::Error>> {
-        let this = self.project();
-        this.writer.poll_write_vectored(cx, bufs)
+        Pin::new(&mut self.get_mut().writer).poll_write_vectored(cx, bufs)
     }
 
     fn is_write_vectored(&self) -> bool {
@@ -291,31 +1432,54 @@ impl tokio::io::AsyncWrite for TcpStream {
     }
 }
 
+/// [`OwnedReadHalf`] is the read half of a [`TcpStream`].
+pub struct OwnedReadHalf {
+    socket: socket::Socket,
+    // Boxed so that the halves, and the stream, are `Unpin`.
+    inner: Pin<Box<ReaderInner>>,
+}
+
+/// The read half of a [`TcpStream`], previously named `TcpStreamReader`.
+pub type TcpStreamReader = OwnedReadHalf;
+
+/// The write half of a [`TcpStream`], previously named `TcpStreamWriter`.
+pub type TcpStreamWriter = OwnedWriteHalf;
+
 pin_project_lite::pin_project! {
-    /// [`TcpStreamReader`] is the read half of a [`TcpStream`].
-    pub struct TcpStreamReader {
-        #[pin]
-        inner: ReadyStream,
+    #[project = ReaderInnerProj]
+    enum ReaderInner {
+        Ready { #[pin] inner: ReadyStream },
+        Completion { #[pin] inner: CompletionReader },
     }
 }
 
+/// [`OwnedWriteHalf`] is the write half of a [`TcpStream`].
+pub struct OwnedWriteHalf {
+    socket: socket::Socket,
+    // Boxed so that the halves, and the stream, are `Unpin`.
+    inner: Pin<Box<WriterInner>>,
+}
+
 pin_project_lite::pin_project! {
-    /// [`TcpStreamWriter`] is the write half of a [`TcpStream`].
-    pub struct TcpStreamWriter {
-        #[pin]
-        inner: ReadyStream,
+    #[project = WriterInnerProj]
+    enum WriterInner {
+        Ready { #[pin] inner: ReadyStream },
+        Completion { #[pin] inner: CompletionWriter },
     }
 }
 
-impl tokio::io::AsyncRead for TcpStreamReader {
+impl tokio::io::AsyncRead for OwnedReadHalf {
     fn poll_read(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        let this = self.project();
+        let inner = match self.get_mut().inner.as_mut().project() {
+            ReaderInnerProj::Ready { inner } => inner,
+            ReaderInnerProj::Completion { inner } => return inner.poll_read(cx, buf),
+        };
 
-        let n = ready!(this.inner.poll_op(
+        let n = ready!(inner.poll_op(
             cx,
             |sock| { unsafe { sock.recv(buf.unfilled_mut()) } },
             socket::READ_FLAGS as u32,
@@ -328,31 +1492,36 @@ impl tokio::io::AsyncRead for TcpStreamReader {
     }
 }
 
-impl tokio::io::AsyncWrite for TcpStreamWriter {
+impl tokio::io::AsyncWrite for OwnedWriteHalf {
     fn poll_write(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        let this = self.project();
-        let n = ready!(this
-            .inner
-            .poll_op(cx, |sock| sock.send(buf), socket::WRITE_FLAGS as u32))?;
+        let inner = match self.get_mut().inner.as_mut().project() {
+            WriterInnerProj::Ready { inner } => inner,
+            WriterInnerProj::Completion { inner } => return inner.poll_write(cx, buf),
+        };
+        let n = ready!(inner.poll_op(cx, |sock| sock.send(buf), socket::WRITE_FLAGS as u32))?;
         Poll::Ready(Ok(n))
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
         use std::io::Write;
-        let this = self.project();
-        ready!(this
-            .inner
-            .poll_op(cx, |mut sock| sock.flush(), socket::WRITE_FLAGS as u32))?;
+        let inner = match self.get_mut().inner.as_mut().project() {
+            WriterInnerProj::Ready { inner } => inner,
+            WriterInnerProj::Completion { inner } => return inner.poll_flush(cx),
+        };
+        ready!(inner.poll_op(cx, |mut sock| sock.flush(), socket::WRITE_FLAGS as u32))?;
         Poll::Ready(Ok(()))
     }
 
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        let this = self.project();
-        ready!(this.inner.poll_op(
+        let inner = match self.get_mut().inner.as_mut().project() {
+            WriterInnerProj::Ready { inner } => inner,
+            WriterInnerProj::Completion { inner } => return inner.poll_shutdown(cx),
+        };
+        ready!(inner.poll_op(
             cx,
             |sock| sock.shutdown(std::net::Shutdown::Write),
             socket::WRITE_FLAGS as u32
@@ -365,8 +1534,11 @@ impl tokio::io::AsyncWrite for TcpStreamWriter {
         cx: &mut Context<'_>,
         bufs: &[io::IoSlice<'_>],
     ) -> Poll<Result<usize, io::Error>> {
-        let this = self.project();
-        let n = ready!(this.inner.poll_op(
+        let inner = match self.get_mut().inner.as_mut().project() {
+            WriterInnerProj::Ready { inner } => inner,
+            WriterInnerProj::Completion { inner } => return inner.poll_write_vectored(cx, bufs),
+        };
+        let n = ready!(inner.poll_op(
             cx,
             |sock| sock.send_vectored(bufs),
             socket::WRITE_FLAGS as u32
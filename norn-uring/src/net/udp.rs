@@ -1,12 +1,101 @@
 //! UDP Protocol Socket
 use std::io;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
 
 use socket2::{Domain, Type};
 
-use crate::buf::{StableBuf, StableBufMut};
+use crate::buf::{StableBuf, StableBufMut, StableBufs, StableBufsMut};
 use crate::bufring::{BufRing, BufRingBuf};
-use crate::net::socket;
+use crate::net::cmsg::ControlMessageBuf;
+use crate::net::socket::{self, RecvMsgBuf, RecvMsgInfo};
+
+/// The number of bytes reserved for control data in each buffer used by
+/// [`UdpSocket::recvmsg_multi`] and by [`UdpSocket::recv_msg`].
+pub const RECVMSG_CONTROL_LEN: u32 = 256;
+
+pin_project_lite::pin_project! {
+    /// A stream of messages received on a [`UdpSocket`].
+    ///
+    /// This is returned by [`UdpSocket::recvmsg_multi`].
+    #[derive(Debug)]
+    pub struct RecvMsgMultiStream<'a> {
+        #[pin]
+        inner: socket::RingStream<'a, socket::RecvMsgMulti>,
+    }
+}
+
+impl Stream for RecvMsgMultiStream<'_> {
+    type Item = io::Result<RecvMsgBuf>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_next(cx)
+    }
+}
+
+/// A builder for UDP sockets, for setting options which must be applied
+/// before the socket is bound.
+#[derive(Debug, Clone, Default)]
+pub struct UdpSocketBuilder {
+    options: socket::SocketOptions,
+}
+
+impl UdpSocketBuilder {
+    /// Creates a new builder with no options set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set value for the SO_REUSEADDR option on the socket.
+    pub fn reuse_address(mut self, reuse: bool) -> Self {
+        self.options.reuse_address = Some(reuse);
+        self
+    }
+
+    /// Set value for the SO_REUSEPORT option on the socket.
+    ///
+    /// This allows multiple sockets to bind to the same address, with the
+    /// kernel distributing incoming datagrams between them.
+    pub fn reuse_port(mut self, reuse: bool) -> Self {
+        self.options.reuse_port = Some(reuse);
+        self
+    }
+
+    /// Set value for the IPV6_V6ONLY option on the socket.
+    pub fn only_v6(mut self, only_v6: bool) -> Self {
+        self.options.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Bind the socket to the given network interface with SO_BINDTODEVICE.
+    pub fn bind_device(mut self, interface: impl AsRef<[u8]>) -> Self {
+        self.options.device = Some(interface.as_ref().to_vec());
+        self
+    }
+
+    /// Set value for the SO_RCVBUF option on the socket.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.options.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set value for the SO_SNDBUF option on the socket.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.options.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Creates a UDP socket bound to the given address.
+    pub async fn bind(mut self, addr: SocketAddr) -> io::Result<UdpSocket> {
+        self.options.local_addr = Some(addr);
+        let inner = self.options.open(Type::DGRAM, None).await?;
+        Ok(UdpSocket { inner })
+    }
+}
 
 /// A UDP socket.
 ///
@@ -22,6 +111,41 @@ impl std::fmt::Debug for UdpSocket {
     }
 }
 
+impl AsRawFd for UdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl AsFd for UdpSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // Safety: the file descriptor is open for as long as the socket.
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+impl FromRawFd for UdpSocket {
+    /// Adopts a datagram socket.
+    ///
+    /// The file descriptor must be in non-blocking mode, and this panics if called
+    /// outside of a driver context.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        let inner = socket::Socket::from_raw_fd(fd);
+        UdpSocket { inner }
+    }
+}
+
+impl IntoRawFd for UdpSocket {
+    /// See [`UdpSocket::into_std`].
+    ///
+    /// This panics if the socket is still in use by an operation.
+    fn into_raw_fd(self) -> RawFd {
+        self.into_std()
+            .expect("socket is still in use by an operation")
+            .into_raw_fd()
+    }
+}
+
 impl UdpSocket {
     /// Creates a UDP socket from the given address.
     pub async fn bind(addr: SocketAddr) -> io::Result<UdpSocket> {
@@ -29,6 +153,31 @@ impl UdpSocket {
         Ok(UdpSocket { inner })
     }
 
+    /// Creates a socket from a standard library socket.
+    ///
+    /// The socket is put in non-blocking mode. This can be used to adopt a socket
+    /// inherited from another process.
+    ///
+    /// This panics if called outside of a driver context.
+    pub fn from_std(socket: std::net::UdpSocket) -> io::Result<UdpSocket> {
+        let inner = socket::Socket::from_socket2(socket.into())?;
+        Ok(UdpSocket { inner })
+    }
+
+    /// Converts the socket into a standard library socket.
+    ///
+    /// The returned socket is in non-blocking mode. Ownership of the file
+    /// descriptor moves to the returned socket, so the conversion fails,
+    /// returning `self`, while an operation on the socket is still pending.
+    /// This includes operation futures which have not been polled or dropped
+    /// yet, and dropped operations which are still being cancelled.
+    pub fn into_std(self) -> Result<std::net::UdpSocket, UdpSocket> {
+        match self.inner.into_socket2() {
+            Ok(socket) => Ok(socket.into()),
+            Err(inner) => Err(UdpSocket { inner }),
+        }
+    }
+
     /// Returns the socket address that this socket was created from.
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
         self.inner.local_addr()
@@ -39,6 +188,148 @@ impl UdpSocket {
         self.inner.peer_addr()
     }
 
+    /// Connects the socket to a remote address.
+    ///
+    /// Once connected, [`UdpSocket::send`] and [`UdpSocket::recv`] can be used to
+    /// send to and receive from the remote address, and datagrams from any other
+    /// address are discarded.
+    pub async fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        self.inner.connect(addr).await
+    }
+
+    /// Sends a single datagram message to the connected remote address.
+    ///
+    /// On success, returns the number of bytes written.
+    ///
+    /// This takes ownership of the buffer provided and will return it back
+    /// once the operation has completed.
+    pub async fn send<B>(&self, buf: B) -> (io::Result<usize>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        self.inner.send(buf).await
+    }
+
+    /// Receives a single datagram message from the connected remote address.
+    /// On success, returns the number of bytes read.
+    ///
+    /// This must be called with a buf of sufficient size to hold the message. If a message
+    /// is too long to fit in the supplied, buffer, excess bytes may be discarded.
+    pub async fn recv<B>(&self, buf: B) -> (io::Result<usize>, B)
+    where
+        B: StableBufMut + 'static,
+    {
+        self.inner.recv(buf).await
+    }
+
+    /// Receives a single datagram message from the connected remote address using a
+    /// buffer from the given ring.
+    ///
+    /// The buffer ring used must contain buffers of sufficient size to hold the message. If
+    /// a message is too long to fit in the supplied, buffer, excess bytes may be discarded.
+    pub async fn recv_ring(&self, ring: &BufRing) -> io::Result<BufRingBuf> {
+        let (buf, _) = self.inner.recv_from_ring(ring).await?;
+        Ok(buf)
+    }
+
+    /// Joins the IPv4 multicast group `multiaddr` on the interface with address `interface`.
+    ///
+    /// If `interface` is [`Ipv4Addr::UNSPECIFIED`], the kernel picks the interface.
+    pub fn join_multicast_v4(&self, multiaddr: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.inner
+            .as_socket()
+            .join_multicast_v4(&multiaddr, &interface)
+    }
+
+    /// Leaves the IPv4 multicast group `multiaddr` on the interface with address `interface`.
+    pub fn leave_multicast_v4(&self, multiaddr: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.inner
+            .as_socket()
+            .leave_multicast_v4(&multiaddr, &interface)
+    }
+
+    /// Joins the IPv6 multicast group `multiaddr` on the interface with index `interface`.
+    ///
+    /// If `interface` is zero, the kernel picks the interface.
+    pub fn join_multicast_v6(&self, multiaddr: Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.inner
+            .as_socket()
+            .join_multicast_v6(&multiaddr, interface)
+    }
+
+    /// Leaves the IPv6 multicast group `multiaddr` on the interface with index `interface`.
+    pub fn leave_multicast_v6(&self, multiaddr: Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.inner
+            .as_socket()
+            .leave_multicast_v6(&multiaddr, interface)
+    }
+
+    /// Set the value of the IP_MULTICAST_LOOP option on this socket.
+    ///
+    /// When enabled, multicast datagrams sent by this socket are looped back
+    /// to local sockets which have joined the group.
+    pub fn set_multicast_loop_v4(&self, enabled: bool) -> io::Result<()> {
+        self.inner.as_socket().set_multicast_loop_v4(enabled)
+    }
+
+    /// Gets the value of the IP_MULTICAST_LOOP option on this socket.
+    pub fn multicast_loop_v4(&self) -> io::Result<bool> {
+        self.inner.as_socket().multicast_loop_v4()
+    }
+
+    /// Set the value of the IPV6_MULTICAST_LOOP option on this socket.
+    pub fn set_multicast_loop_v6(&self, enabled: bool) -> io::Result<()> {
+        self.inner.as_socket().set_multicast_loop_v6(enabled)
+    }
+
+    /// Gets the value of the IPV6_MULTICAST_LOOP option on this socket.
+    pub fn multicast_loop_v6(&self) -> io::Result<bool> {
+        self.inner.as_socket().multicast_loop_v6()
+    }
+
+    /// Set the value of the IP_MULTICAST_TTL option on this socket.
+    ///
+    /// This limits the number of hops multicast datagrams sent by this socket
+    /// may take. The default of 1 keeps them on the local network.
+    pub fn set_multicast_ttl_v4(&self, ttl: u32) -> io::Result<()> {
+        self.inner.as_socket().set_multicast_ttl_v4(ttl)
+    }
+
+    /// Gets the value of the IP_MULTICAST_TTL option on this socket.
+    pub fn multicast_ttl_v4(&self) -> io::Result<u32> {
+        self.inner.as_socket().multicast_ttl_v4()
+    }
+
+    /// Set the value of the IPV6_MULTICAST_HOPS option on this socket.
+    ///
+    /// This is the IPv6 equivalent of [`UdpSocket::set_multicast_ttl_v4`].
+    pub fn set_multicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        self.inner.as_socket().set_multicast_hops_v6(hops)
+    }
+
+    /// Gets the value of the IPV6_MULTICAST_HOPS option on this socket.
+    pub fn multicast_hops_v6(&self) -> io::Result<u32> {
+        self.inner.as_socket().multicast_hops_v6()
+    }
+
+    /// Set the value of the IP_MULTICAST_IF option on this socket, selecting the
+    /// interface multicast datagrams are sent from by its address.
+    pub fn set_multicast_if_v4(&self, interface: Ipv4Addr) -> io::Result<()> {
+        self.inner.as_socket().set_multicast_if_v4(&interface)
+    }
+
+    /// Set the value of the SO_BROADCAST option on this socket.
+    ///
+    /// When enabled, this socket is allowed to send datagrams to a broadcast address.
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        self.inner.as_socket().set_broadcast(broadcast)
+    }
+
+    /// Gets the value of the SO_BROADCAST option on this socket.
+    pub fn broadcast(&self) -> io::Result<bool> {
+        self.inner.as_socket().broadcast()
+    }
+
     /// Sends a single datagram message on the socket to the given address.
     ///
     /// On success, returns the number of bytes written.
@@ -52,6 +343,18 @@ impl UdpSocket {
         self.inner.send_to(buf, addr).await
     }
 
+    /// Sends a single datagram message on the socket to the given address,
+    /// without copying the buffer into the kernel.
+    ///
+    /// This is equivalent to [`UdpSocket::send_to`], but the buffer is only
+    /// returned once the kernel no longer references it.
+    pub async fn send_to_zc<B>(&self, buf: B, addr: SocketAddr) -> (io::Result<usize>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        self.inner.send_zc(buf, Some(addr)).await
+    }
+
     /// Receives a single datagram message on the socket. On success, returns the number
     /// of bytes read and the origin.
     ///
@@ -73,6 +376,147 @@ impl UdpSocket {
         self.inner.recv_from_ring(ring).await
     }
 
+    /// Sends a single message on the socket, gathered from the given buffers.
+    ///
+    /// The message is sent to `addr`, or to the connected peer if `addr` is `None`.
+    /// Control messages such as [`ControlMessage::SegmentSize`] are sent along with
+    /// the message. With UDP GSO, a single call sends the buffers as many datagrams
+    /// of the given segment size.
+    ///
+    /// On success, returns the number of bytes written. This takes ownership of the
+    /// buffers provided and will return them back once the operation has completed.
+    ///
+    /// [`ControlMessage::SegmentSize`]: crate::net::cmsg::ControlMessage::SegmentSize
+    pub async fn send_msg<V>(
+        &self,
+        bufs: V,
+        addr: Option<SocketAddr>,
+        control: &ControlMessageBuf,
+    ) -> (io::Result<usize>, V)
+    where
+        V: StableBufs,
+    {
+        self.inner
+            .send_msg(bufs, addr.map(Into::into), control)
+            .await
+    }
+
+    /// Sends a single message on the socket, gathered from the given buffers,
+    /// without copying them into the kernel.
+    ///
+    /// This is equivalent to [`UdpSocket::send_msg`], but the buffers are only
+    /// returned once the kernel no longer references them.
+    pub async fn send_msg_zc<V>(
+        &self,
+        bufs: V,
+        addr: Option<SocketAddr>,
+        control: &ControlMessageBuf,
+    ) -> (io::Result<usize>, V)
+    where
+        V: StableBufs,
+    {
+        self.inner
+            .send_msg_zc(bufs, addr.map(Into::into), control)
+            .await
+    }
+
+    /// Receives a single message on the socket, scattered into the given buffers.
+    ///
+    /// On success, returns the length, origin and control messages of the message.
+    /// Up to [`RECVMSG_CONTROL_LEN`] bytes of control messages are received. The
+    /// buffers are filled in order, and each buffer is marked as initialized up to
+    /// the number of bytes written into it.
+    pub async fn recv_msg<V>(&self, bufs: V) -> (io::Result<RecvMsgInfo>, V)
+    where
+        V: StableBufsMut,
+    {
+        self.inner
+            .recv_msg(bufs, RECVMSG_CONTROL_LEN as usize, 0)
+            .await
+    }
+
+    /// Returns a stream of messages received on the socket, using buffers from
+    /// the given buffer ring.
+    ///
+    /// Each message carries its payload, source address and any control messages
+    /// enabled on the socket, such as with [`UdpSocket::set_recv_pktinfo`],
+    /// [`UdpSocket::set_recv_timestamp`] or [`UdpSocket::set_gro`]. Buffers in the
+    /// ring must be large enough to hold the message header, the source address and
+    /// [`RECVMSG_CONTROL_LEN`] bytes of control data in addition to the payload.
+    ///
+    /// A single multishot receive is kept armed for the lifetime of the stream,
+    /// and is transparently re-armed if the kernel stops it, for example because
+    /// the ring ran out of buffers.
+    pub fn recvmsg_multi<'a>(&'a self, ring: &'a BufRing) -> RecvMsgMultiStream<'a> {
+        RecvMsgMultiStream {
+            inner: self.inner.recvmsg_multi(ring, RECVMSG_CONTROL_LEN),
+        }
+    }
+
+    /// Set the IP_PKTINFO or IPV6_RECVPKTINFO option on this socket, depending on its
+    /// address family. Both are set on IPv6 sockets which also receive IPv4 packets,
+    /// that is when IPV6_V6ONLY is not set.
+    ///
+    /// When enabled, received messages carry a [`ControlMessage::PacketInfo`] with the
+    /// destination address and interface of the packet.
+    ///
+    /// [`ControlMessage::PacketInfo`]: crate::net::cmsg::ControlMessage::PacketInfo
+    pub fn set_recv_pktinfo(&self, enabled: bool) -> io::Result<()> {
+        let value = enabled as libc::c_int;
+        if self.local_addr()?.is_ipv4() {
+            return self
+                .inner
+                .set_option(libc::IPPROTO_IP, libc::IP_PKTINFO, &value);
+        }
+        self.inner
+            .set_option(libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, &value)?;
+        // IPv4 packets received by a dual-stack socket only carry IP_PKTINFO.
+        let only_v6: libc::c_int = self
+            .inner
+            .get_option(libc::IPPROTO_IPV6, libc::IPV6_V6ONLY)?;
+        if only_v6 == 0 {
+            self.inner
+                .set_option(libc::IPPROTO_IP, libc::IP_PKTINFO, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Set the IP_RECVTOS option on this socket.
+    ///
+    /// When enabled, received messages carry a [`ControlMessage::Tos`] with the type
+    /// of service field of the packet.
+    ///
+    /// [`ControlMessage::Tos`]: crate::net::cmsg::ControlMessage::Tos
+    pub fn set_recv_tos(&self, enabled: bool) -> io::Result<()> {
+        let value = enabled as libc::c_int;
+        self.inner
+            .set_option(libc::IPPROTO_IP, libc::IP_RECVTOS, &value)
+    }
+
+    /// Set the SO_TIMESTAMPNS option on this socket.
+    ///
+    /// When enabled, received messages carry a [`ControlMessage::Timestamp`] with the
+    /// time the packet was received.
+    ///
+    /// [`ControlMessage::Timestamp`]: crate::net::cmsg::ControlMessage::Timestamp
+    pub fn set_recv_timestamp(&self, enabled: bool) -> io::Result<()> {
+        let value = enabled as libc::c_int;
+        self.inner
+            .set_option(libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, &value)
+    }
+
+    /// Set the UDP_GRO option on this socket.
+    ///
+    /// When enabled, the kernel may coalesce multiple datagrams from the same flow into
+    /// a single message, which carries a [`ControlMessage::GroSegmentSize`] with the size
+    /// of the original datagrams.
+    ///
+    /// [`ControlMessage::GroSegmentSize`]: crate::net::cmsg::ControlMessage::GroSegmentSize
+    pub fn set_gro(&self, enabled: bool) -> io::Result<()> {
+        let value = enabled as libc::c_int;
+        self.inner.set_option(libc::SOL_UDP, libc::UDP_GRO, &value)
+    }
+
     /// Close the socket.
     ///
     /// This will wait for all pending operations to complete before closing the socket.
@@ -0,0 +1,494 @@
+//! Unix Domain Sockets
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use socket2::{Domain, SockAddr, Type};
+
+use crate::buf::{StableBuf, StableBufMut};
+use crate::net::cmsg::{self, ControlMessage, ControlMessageBuf};
+use crate::net::socket::{self, Accept};
+use crate::operation::Op;
+
+/// The maximum number of file descriptors which can be passed in a single message,
+/// `SCM_MAX_FD` in the kernel.
+pub const MAX_FDS: usize = 253;
+
+/// An address associated with a Unix socket.
+///
+/// An address is either a path in the filesystem, a name in the Linux
+/// abstract namespace, or unnamed.
+#[derive(Clone)]
+pub struct UnixSocketAddr {
+    inner: SockAddr,
+}
+
+impl std::fmt::Debug for UnixSocketAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = self.as_pathname() {
+            write!(f, "{path:?} (pathname)")
+        } else if let Some(name) = self.as_abstract_name() {
+            write!(f, "\"{}\" (abstract)", name.escape_ascii())
+        } else {
+            write!(f, "(unnamed)")
+        }
+    }
+}
+
+impl UnixSocketAddr {
+    /// Creates an address from a path in the filesystem.
+    pub fn from_pathname(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path.as_os_str().as_bytes().first() == Some(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "pathname must not start with a null byte",
+            ));
+        }
+        let inner = SockAddr::unix(path)?;
+        Ok(Self { inner })
+    }
+
+    /// Creates an address in the Linux abstract namespace.
+    ///
+    /// Abstract addresses are not bound to the filesystem, and disappear once
+    /// all sockets referencing them are closed.
+    pub fn from_abstract_name(name: impl AsRef<[u8]>) -> io::Result<Self> {
+        let mut path = vec![0];
+        path.extend_from_slice(name.as_ref());
+        let path = Path::new(std::ffi::OsStr::from_bytes(&path));
+        let inner = SockAddr::unix(path)?;
+        Ok(Self { inner })
+    }
+
+    /// Returns the path of the address, if it is a pathname address.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        self.inner.as_pathname()
+    }
+
+    /// Returns the name of the address, if it is an abstract address.
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        self.inner.as_abstract_namespace()
+    }
+
+    /// Returns true if the address is unnamed.
+    pub fn is_unnamed(&self) -> bool {
+        self.inner.is_unnamed()
+    }
+}
+
+/// The credentials of the process on the other end of a Unix socket.
+///
+/// These are the credentials at the time the connection was established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UCred {
+    pid: i32,
+    uid: u32,
+    gid: u32,
+}
+
+impl UCred {
+    /// Returns the process id of the peer.
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// Returns the user id of the peer.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the group id of the peer.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+}
+
+/// A Unix socket listener.
+///
+/// A UnixListener can be used to accept incoming Unix stream connections.
+pub struct UnixListener {
+    socket: socket::Socket,
+}
+
+impl std::fmt::Debug for UnixListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnixListener").finish()
+    }
+}
+
+impl UnixListener {
+    /// Creates a Unix listener bound to the specified address.
+    pub async fn bind(addr: UnixSocketAddr, backlog: u32) -> io::Result<UnixListener> {
+        let socket = socket::Socket::bind_addr(&addr.inner, Domain::UNIX, Type::STREAM).await?;
+        socket.listen(backlog)?;
+        Ok(UnixListener { socket })
+    }
+
+    /// Returns the local address that this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        let inner = self.socket.local_sockaddr()?;
+        Ok(UnixSocketAddr { inner })
+    }
+
+    /// Accepts a new incoming connection to this listener.
+    pub async fn accept(&self) -> io::Result<(UnixStream, UnixSocketAddr)> {
+        let (socket, inner) = self.socket.accept_addr().await?;
+        Ok((UnixStream { socket }, UnixSocketAddr { inner }))
+    }
+
+    /// Returns a stream of incoming connections.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming {
+            listener: &self.socket,
+            current: None,
+        }
+    }
+
+    /// Closes the listener.
+    ///
+    /// This does not remove the socket file of a pathname address.
+    pub async fn close(self) -> io::Result<()> {
+        self.socket.close().await
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A stream of connections accepted by a [`UnixListener`].
+    pub struct Incoming<'a> {
+        listener: &'a socket::Socket,
+        #[pin]
+        current: Option<Op<Accept<true>>>,
+    }
+}
+
+impl std::fmt::Debug for Incoming<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Incoming").finish()
+    }
+}
+
+impl Stream for Incoming<'_> {
+    type Item = io::Result<UnixStream>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if let Some(current) = this.current.as_mut().as_pin_mut() {
+                match ready!(current.poll_next(cx)) {
+                    Some(Err(err)) => {
+                        this.current.set(None);
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    Some(Ok(fd)) => {
+                        let socket = socket::Socket::from_fd(fd);
+                        return Poll::Ready(Some(Ok(UnixStream { socket })));
+                    }
+                    None => {
+                        this.current.set(None);
+                        return Poll::Ready(None);
+                    }
+                }
+            }
+            this.current.set(Some(this.listener.accept_multi()));
+        }
+    }
+}
+
+/// A connected Unix stream socket.
+///
+/// Data is sent and received using owned buffers. File descriptors can be
+/// passed alongside data with [`UnixStream::send_with_fds`] and
+/// [`UnixStream::recv_with_fds`].
+pub struct UnixStream {
+    socket: socket::Socket,
+}
+
+impl std::fmt::Debug for UnixStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnixStream").finish()
+    }
+}
+
+impl UnixStream {
+    /// Connects to the socket at the specified address.
+    pub async fn connect(addr: UnixSocketAddr) -> io::Result<UnixStream> {
+        let socket = socket::Socket::open(Domain::UNIX, Type::STREAM, None).await?;
+        socket.connect_addr(addr.inner).await?;
+        Ok(UnixStream { socket })
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
+        let (a, b) = socket::Socket::pair(Domain::UNIX, Type::STREAM)?;
+        Ok((UnixStream { socket: a }, UnixStream { socket: b }))
+    }
+
+    /// Returns the local address of this socket.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        let inner = self.socket.local_sockaddr()?;
+        Ok(UnixSocketAddr { inner })
+    }
+
+    /// Returns the address of the peer of this socket.
+    pub fn peer_addr(&self) -> io::Result<UnixSocketAddr> {
+        let inner = self.socket.peer_sockaddr()?;
+        Ok(UnixSocketAddr { inner })
+    }
+
+    /// Returns the credentials of the peer, from `SO_PEERCRED`.
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        let cred: libc::ucred = self
+            .socket
+            .get_option(libc::SOL_SOCKET, libc::SO_PEERCRED)?;
+        Ok(UCred {
+            pid: cred.pid,
+            uid: cred.uid,
+            gid: cred.gid,
+        })
+    }
+
+    /// Recv data into the given buffer.
+    ///
+    /// This takes ownership of the buffer, and returns future which resolves
+    /// to a tuple of the original buffer and the number of bytes read.
+    pub fn recv<B: StableBufMut>(&self, buf: B) -> Op<socket::Recv<B>> {
+        self.socket.recv(buf)
+    }
+
+    /// Send data from the given buffer.
+    ///
+    /// This takes ownership of the buffer, and returns a future which resolves
+    /// to a tuple of the original buffer and the number of bytes sent.
+    pub fn send<B: StableBuf>(&self, buf: B) -> Op<socket::Send<B>> {
+        self.socket.send(buf)
+    }
+
+    /// Send data from the given buffer along with the given file descriptors.
+    ///
+    /// The file descriptors are duplicated into the receiving process, and
+    /// remain open in this process. At most [`MAX_FDS`] can be sent at once.
+    pub async fn send_with_fds<B>(&self, buf: B, fds: &[BorrowedFd<'_>]) -> (io::Result<usize>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        send_with_fds(&self.socket, buf, None, fds).await
+    }
+
+    /// Recv data into the given buffer, along with any file descriptors sent
+    /// with it.
+    ///
+    /// Received file descriptors have close-on-exec set. If the kernel could not
+    /// pass every file descriptor, for example because the process ran out of file
+    /// descriptors, the ones received are closed and an error of kind
+    /// [`io::ErrorKind::InvalidData`] is returned. The data is still consumed.
+    pub async fn recv_with_fds<B>(&self, buf: B) -> (io::Result<(usize, Vec<OwnedFd>)>, B)
+    where
+        B: StableBufMut + 'static,
+    {
+        let (res, buf) = recv_with_fds(&self.socket, buf).await;
+        (res.map(|(n, fds, _)| (n, fds)), buf)
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub async fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
+        self.socket.shutdown(how).await
+    }
+
+    /// Close the socket.
+    pub async fn close(self) -> io::Result<()> {
+        self.socket.close().await
+    }
+}
+
+/// A Unix datagram socket.
+pub struct UnixDatagram {
+    socket: socket::Socket,
+}
+
+impl std::fmt::Debug for UnixDatagram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnixDatagram").finish()
+    }
+}
+
+impl UnixDatagram {
+    /// Creates a Unix datagram socket bound to the given address.
+    pub async fn bind(addr: UnixSocketAddr) -> io::Result<UnixDatagram> {
+        let socket = socket::Socket::bind_addr(&addr.inner, Domain::UNIX, Type::DGRAM).await?;
+        Ok(UnixDatagram { socket })
+    }
+
+    /// Creates a Unix datagram socket which is not bound to any address.
+    pub async fn unbound() -> io::Result<UnixDatagram> {
+        let socket = socket::Socket::open(Domain::UNIX, Type::DGRAM, None).await?;
+        Ok(UnixDatagram { socket })
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
+        let (a, b) = socket::Socket::pair(Domain::UNIX, Type::DGRAM)?;
+        Ok((UnixDatagram { socket: a }, UnixDatagram { socket: b }))
+    }
+
+    /// Connects the socket to the specified address.
+    ///
+    /// [`UnixDatagram::send`] and [`UnixDatagram::recv`] can then be used to
+    /// send to and receive from the address.
+    pub async fn connect(&self, addr: UnixSocketAddr) -> io::Result<()> {
+        self.socket.connect_addr(addr.inner).await
+    }
+
+    /// Returns the local address of this socket.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        let inner = self.socket.local_sockaddr()?;
+        Ok(UnixSocketAddr { inner })
+    }
+
+    /// Returns the address of the peer of this socket, if it is connected.
+    pub fn peer_addr(&self) -> io::Result<UnixSocketAddr> {
+        let inner = self.socket.peer_sockaddr()?;
+        Ok(UnixSocketAddr { inner })
+    }
+
+    /// Sends a single datagram message on the socket to the given address.
+    ///
+    /// On success, returns the number of bytes written.
+    pub async fn send_to<B>(&self, buf: B, addr: &UnixSocketAddr) -> (io::Result<usize>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        send_with_fds(&self.socket, buf, Some(addr.inner.clone()), &[]).await
+    }
+
+    /// Receives a single datagram message on the socket. On success, returns the number
+    /// of bytes read and the origin.
+    pub async fn recv_from<B>(&self, buf: B) -> (io::Result<(usize, UnixSocketAddr)>, B)
+    where
+        B: StableBufMut + 'static,
+    {
+        let (res, buf) = recv_with_fds(&self.socket, buf).await;
+        (res.map(|(n, _, addr)| (n, addr)), buf)
+    }
+
+    /// Sends a single datagram message to the connected peer.
+    pub fn send<B: StableBuf>(&self, buf: B) -> Op<socket::Send<B>> {
+        self.socket.send(buf)
+    }
+
+    /// Receives a single datagram message from the connected peer.
+    pub fn recv<B: StableBufMut>(&self, buf: B) -> Op<socket::Recv<B>> {
+        self.socket.recv(buf)
+    }
+
+    /// Sends a single datagram message to the connected peer along with the
+    /// given file descriptors.
+    ///
+    /// See [`UnixStream::send_with_fds`].
+    pub async fn send_with_fds<B>(&self, buf: B, fds: &[BorrowedFd<'_>]) -> (io::Result<usize>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        send_with_fds(&self.socket, buf, None, fds).await
+    }
+
+    /// Receives a single datagram message along with any file descriptors sent
+    /// with it.
+    ///
+    /// See [`UnixStream::recv_with_fds`].
+    pub async fn recv_with_fds<B>(&self, buf: B) -> (io::Result<(usize, Vec<OwnedFd>)>, B)
+    where
+        B: StableBufMut + 'static,
+    {
+        let (res, buf) = recv_with_fds(&self.socket, buf).await;
+        (res.map(|(n, fds, _)| (n, fds)), buf)
+    }
+
+    /// Close the socket.
+    pub async fn close(self) -> io::Result<()> {
+        self.socket.close().await
+    }
+}
+
+async fn send_with_fds<B>(
+    socket: &socket::Socket,
+    buf: B,
+    addr: Option<SockAddr>,
+    fds: &[BorrowedFd<'_>],
+) -> (io::Result<usize>, B)
+where
+    B: StableBuf + 'static,
+{
+    let mut control = ControlMessageBuf::new();
+    if !fds.is_empty() {
+        if fds.len() > MAX_FDS {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("at most {MAX_FDS} file descriptors can be sent at once"),
+            );
+            return (Err(err), buf);
+        }
+        let data: Vec<u8> = fds
+            .iter()
+            .flat_map(|fd| fd.as_raw_fd().to_ne_bytes())
+            .collect();
+        let rights = ControlMessage::Other {
+            level: libc::SOL_SOCKET,
+            ty: libc::SCM_RIGHTS,
+            data: &data,
+        };
+        if let Err(err) = control.push(rights) {
+            return (Err(err), buf);
+        }
+    }
+    let (res, mut bufs) = socket.send_msg(vec![buf], addr, &control).await;
+    (res, bufs.pop().unwrap())
+}
+
+async fn recv_with_fds<B>(
+    socket: &socket::Socket,
+    buf: B,
+) -> (io::Result<(usize, Vec<OwnedFd>, UnixSocketAddr)>, B)
+where
+    B: StableBufMut + 'static,
+{
+    let control_len = cmsg::cmsg_space(MAX_FDS * std::mem::size_of::<libc::c_int>());
+    let flags = libc::MSG_CMSG_CLOEXEC as u32;
+    let (res, mut bufs) = socket.recv_msg(vec![buf], control_len, flags).await;
+    let buf = bufs.pop().unwrap();
+    let info = match res {
+        Ok(info) => info,
+        Err(err) => return (Err(err), buf),
+    };
+    let mut fds = vec![];
+    for msg in info.control_messages() {
+        if let ControlMessage::Other {
+            level: libc::SOL_SOCKET,
+            ty: libc::SCM_RIGHTS,
+            data,
+        } = msg
+        {
+            for fd in data.chunks_exact(std::mem::size_of::<libc::c_int>()) {
+                let fd = libc::c_int::from_ne_bytes(fd.try_into().unwrap());
+                // Safety: The kernel installed the file descriptor into this process,
+                // and ownership is transferred to the caller.
+                fds.push(unsafe { OwnedFd::from_raw_fd(fd) });
+            }
+        }
+    }
+    if info.is_control_truncated() {
+        let err = io::Error::new(
+            io::ErrorKind::InvalidData,
+            "control messages were truncated, file descriptors were lost",
+        );
+        return (Err(err), buf);
+    }
+    let addr = UnixSocketAddr {
+        inner: info.source_addr().clone(),
+    };
+    (Ok((info.len(), fds, addr)), buf)
+}
@@ -131,6 +131,31 @@ where
     }
 }
 
+impl<T> Op<T>
+where
+    T: Multishot + 'static,
+{
+    /// Request cancellation of the operation without dropping it.
+    ///
+    /// Items posted before the cancellation takes effect are still yielded, after
+    /// which the stream ends. Returns false if the operation was never submitted,
+    /// in which case it can be dropped without losing any items.
+    pub(crate) fn cancel(self: Pin<&mut Self>) -> bool {
+        let this = self.project();
+        match this.stage.project() {
+            StageProj::Unsubmitted { .. } => false,
+            StageProj::Submitted { inner } => {
+                if !*this.completed {
+                    let user_data = inner.inner.inner.as_raw_usize();
+                    let criteria = CancelBuilder::user_data(user_data as u64);
+                    let _ = this.reactor.cancel(criteria, false);
+                }
+                true
+            }
+        }
+    }
+}
+
 impl<T> futures_core::Stream for Op<T>
 where
     T: Multishot + 'static,
@@ -183,7 +208,11 @@ where
     fn pop_completion(&self) -> Option<CQEResult> {
         let handle = self.untyped();
         let mut completions = handle.header().completions().borrow_mut();
-        completions.pop()
+        // Completions must be yielded in the order they were received.
+        if completions.is_empty() {
+            return None;
+        }
+        Some(completions.remove(0))
     }
 
     /// Returns true if this operation is complete.
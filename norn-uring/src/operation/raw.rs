@@ -114,6 +114,12 @@ impl CQEResult {
     pub(crate) fn more(&self) -> bool {
         io_uring::cqueue::more(self.flags)
     }
+
+    /// Returns true if this is a zero-copy notification, indicating that the
+    /// kernel has released the buffer used by the operation.
+    pub(crate) fn notif(&self) -> bool {
+        io_uring::cqueue::notif(self.flags)
+    }
 }
 
 /// [`RawOpHandle`] is a reference to an operation that is in
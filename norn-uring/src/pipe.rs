@@ -0,0 +1,412 @@
+//! Anonymous pipes and zero-copy data transfer.
+//!
+//! [`splice(2)`] moves data between a pipe and another file descriptor
+//! without copying it through user space, and [`tee(2)`] duplicates data
+//! between two pipes without consuming it. Both are available as io_uring
+//! operations on [`File`](crate::fs::File),
+//! [`TcpSocket`](crate::net::TcpSocket) and the pipe types in this module.
+//!
+//! [`copy`] combines these into a helper which moves data between any two
+//! [`Splicable`] types through an intermediate kernel pipe.
+//!
+//! [`splice(2)`]: https://man7.org/linux/man-pages/man2/splice.2.html
+//! [`tee(2)`]: https://man7.org/linux/man-pages/man2/tee.2.html
+use std::pin::Pin;
+use std::{cmp, io};
+
+use io_uring::opcode;
+
+use crate::buf::{StableBuf, StableBufMut};
+use crate::fd::{FdKind, NornFd};
+use crate::fs::file::{ReadAt, WriteAt};
+use crate::net::socket;
+use crate::operation::{CQEResult, Operation, Singleshot};
+use crate::Handle;
+
+/// The offset used for file descriptors which have no position, or to use
+/// and update the current file position.
+const NO_OFFSET: i64 = -1;
+
+/// The maximum number of bytes moved through the pipe by [`copy`] at a time.
+///
+/// This matches the default pipe capacity on Linux.
+const COPY_CHUNK: u64 = 64 * 1024;
+
+/// Create a new anonymous pipe.
+///
+/// Returns the read and write ends of the pipe.
+pub fn pipe() -> io::Result<(PipeReader, PipeWriter)> {
+    let mut fds = [0; 2];
+    // Safety: `fds` is valid for writes of two file descriptors.
+    let res = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let handle = Handle::current();
+    let reader = PipeReader {
+        fd: NornFd::from_fd(fds[0]),
+        handle: handle.clone(),
+    };
+    let writer = PipeWriter {
+        fd: NornFd::from_fd(fds[1]),
+        handle,
+    };
+    Ok((reader, writer))
+}
+
+/// The read end of a pipe, created by [`pipe`].
+pub struct PipeReader {
+    fd: NornFd,
+    handle: Handle,
+}
+
+/// The write end of a pipe, created by [`pipe`].
+pub struct PipeWriter {
+    fd: NornFd,
+    handle: Handle,
+}
+
+impl std::fmt::Debug for PipeReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipeReader").finish()
+    }
+}
+
+impl std::fmt::Debug for PipeWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipeWriter").finish()
+    }
+}
+
+impl PipeReader {
+    /// Read bytes from the pipe into the specified buffer.
+    pub async fn read<B>(&self, buf: B) -> (io::Result<usize>, B)
+    where
+        B: StableBufMut + 'static,
+    {
+        let read = ReadAt::new(self.fd.clone(), buf, NO_OFFSET as u64);
+        self.handle.submit(read).await
+    }
+
+    /// Move up to `len` bytes from this pipe into another pipe.
+    ///
+    /// Returns the number of bytes moved, 0 indicates that the write end of
+    /// this pipe has been closed.
+    pub async fn splice_to(&self, dst: &PipeWriter, len: u32) -> io::Result<usize> {
+        let splice = Splice::new(self.fd.clone(), NO_OFFSET, dst.fd.clone(), NO_OFFSET, len);
+        self.handle.submit(splice).await
+    }
+
+    /// Duplicate up to `len` bytes from this pipe into another pipe without
+    /// consuming them.
+    ///
+    /// The duplicated data can still be read from this pipe.
+    pub async fn tee(&self, dst: &PipeWriter, len: u32) -> io::Result<usize> {
+        let tee = Tee::new(self.fd.clone(), dst.fd.clone(), len);
+        self.handle.submit(tee).await
+    }
+
+    pub(crate) fn fd(&self) -> &NornFd {
+        &self.fd
+    }
+
+    /// Close the read end of the pipe.
+    pub async fn close(self) -> io::Result<()> {
+        self.fd.close().await
+    }
+}
+
+impl PipeWriter {
+    /// Write the specified buffer to the pipe.
+    pub async fn write<B>(&self, buf: B) -> (io::Result<usize>, B)
+    where
+        B: StableBuf + 'static,
+    {
+        let write = WriteAt::new(self.fd.clone(), buf, NO_OFFSET as u64);
+        self.handle.submit(write).await
+    }
+
+    pub(crate) fn fd(&self) -> &NornFd {
+        &self.fd
+    }
+
+    /// Close the write end of the pipe.
+    ///
+    /// Readers will observe EOF once all buffered data has been read.
+    pub async fn close(self) -> io::Result<()> {
+        self.fd.close().await
+    }
+}
+
+/// [`Splicable`] is implemented by types which can be used as the source or
+/// destination of [`copy`].
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait Splicable: sealed::Sealed {}
+
+pub(crate) mod sealed {
+    /// A reference to the file descriptor of a [`Splicable`](super::Splicable) type.
+    #[derive(Debug)]
+    pub struct FdRef<'a>(pub(crate) &'a crate::fd::NornFd);
+
+    pub trait Sealed {
+        fn splice_fd(&self) -> FdRef<'_>;
+    }
+}
+
+impl Splicable for crate::fs::File {}
+
+impl sealed::Sealed for crate::fs::File {
+    fn splice_fd(&self) -> sealed::FdRef<'_> {
+        sealed::FdRef(self.fd())
+    }
+}
+
+impl Splicable for crate::net::TcpSocket {}
+
+impl sealed::Sealed for crate::net::TcpSocket {
+    fn splice_fd(&self) -> sealed::FdRef<'_> {
+        sealed::FdRef(self.socket().fd())
+    }
+}
+
+impl Splicable for PipeReader {}
+
+impl sealed::Sealed for PipeReader {
+    fn splice_fd(&self) -> sealed::FdRef<'_> {
+        sealed::FdRef(&self.fd)
+    }
+}
+
+impl Splicable for PipeWriter {}
+
+impl sealed::Sealed for PipeWriter {
+    fn splice_fd(&self) -> sealed::FdRef<'_> {
+        sealed::FdRef(&self.fd)
+    }
+}
+
+/// Copy up to `len` bytes from `src` to `dst` through a kernel pipe.
+///
+/// The data is spliced from `src` into an intermediate pipe, then from the
+/// pipe into `dst`, so it is never copied into user space.
+///
+/// `src_offset` and `dst_offset` give the file offsets to copy from and to,
+/// and must be `None` for sockets and pipes. With `None`, the current file
+/// position is used and advanced, as with `read(2)` and `write(2)`. Note that
+/// the positional methods on [`File`](crate::fs::File) do not move the file
+/// position, so a newly opened file is copied from the start.
+///
+/// Returns the result along with the number of bytes written to `dst`, which
+/// is less than `len` only if EOF was reached on `src` or an error occurred.
+/// Bytes are taken from `src` before they are written to `dst`, so if writing
+/// to `dst` fails, up to 64 KiB may have been consumed from `src` without
+/// reaching `dst`. Those bytes are discarded with the intermediate pipe.
+pub async fn copy<S, D>(
+    src: &S,
+    src_offset: Option<u64>,
+    dst: &D,
+    dst_offset: Option<u64>,
+    len: u64,
+) -> (io::Result<()>, u64)
+where
+    S: Splicable + ?Sized,
+    D: Splicable + ?Sized,
+{
+    let (reader, writer) = match pipe() {
+        Ok(pipe) => pipe,
+        Err(err) => return (Err(err), 0),
+    };
+    let mut copied = 0;
+    let mut offsets = (src_offset, dst_offset);
+    let res = copy_through(&reader, &writer, src, dst, &mut offsets, len, &mut copied).await;
+    if let Err(err) = res {
+        return (Err(err), copied);
+    }
+    if let Err(err) = reader.close().await {
+        return (Err(err), copied);
+    }
+    (writer.close().await, copied)
+}
+
+/// The loop of [`copy`], adding the number of bytes written to `dst` to `copied`.
+async fn copy_through<S, D>(
+    reader: &PipeReader,
+    writer: &PipeWriter,
+    src: &S,
+    dst: &D,
+    (src_offset, dst_offset): &mut (Option<u64>, Option<u64>),
+    len: u64,
+    copied: &mut u64,
+) -> io::Result<()>
+where
+    S: Splicable + ?Sized,
+    D: Splicable + ?Sized,
+{
+    let handle = Handle::current();
+    while *copied < len {
+        let chunk = cmp::min(len - *copied, COPY_CHUNK) as u32;
+        let src = src.splice_fd().0;
+        let wait = (src, socket::READ_FLAGS);
+        let off_in = src_offset.map_or(NO_OFFSET, |off| off as i64);
+        let n = splice(&handle, src, off_in, &writer.fd, NO_OFFSET, chunk, wait).await?;
+        if n == 0 {
+            break;
+        }
+        if let Some(off) = src_offset {
+            *off += n as u64;
+        }
+        let mut remaining = n;
+        while remaining > 0 {
+            let dst = dst.splice_fd().0;
+            let wait = (dst, socket::WRITE_FLAGS);
+            let len = remaining as u32;
+            let off_out = dst_offset.map_or(NO_OFFSET, |off| off as i64);
+            match splice(&handle, &reader.fd, NO_OFFSET, dst, off_out, len, wait).await? {
+                0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                n => {
+                    remaining -= n;
+                    *copied += n as u64;
+                    if let Some(off) = dst_offset {
+                        *off += n as u64;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Splice between two file descriptors, retrying once `wait` is ready if
+/// the operation would block.
+///
+/// Sockets are non-blocking, so splicing to or from a socket which is not
+/// ready fails with `EAGAIN` rather than waiting. `wait` should be the
+/// non-pipe side of the splice, along with the poll events to wait for.
+pub(crate) async fn splice(
+    handle: &Handle,
+    fd_in: &NornFd,
+    off_in: i64,
+    fd_out: &NornFd,
+    off_out: i64,
+    len: u32,
+    wait: (&NornFd, i16),
+) -> io::Result<usize> {
+    loop {
+        let op = Splice::new(fd_in.clone(), off_in, fd_out.clone(), off_out, len);
+        match handle.submit(op).await {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                let (fd, events) = wait;
+                let poll = socket::Poll::<false>::new(fd.clone(), events as u32);
+                // Errors and hangups are reported by retrying the splice.
+                let _ = handle.submit(poll).await?;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Move data between two file descriptors, one of which must be a pipe.
+pub(crate) struct Splice {
+    fd_in: NornFd,
+    off_in: i64,
+    fd_out: NornFd,
+    off_out: i64,
+    len: u32,
+}
+
+impl std::fmt::Debug for Splice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Splice")
+            .field("off_in", &self.off_in)
+            .field("off_out", &self.off_out)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl Splice {
+    /// An offset of -1 uses the current file position, and must be used for
+    /// pipes and sockets.
+    pub(crate) fn new(fd_in: NornFd, off_in: i64, fd_out: NornFd, off_out: i64, len: u32) -> Self {
+        Self {
+            fd_in,
+            off_in,
+            fd_out,
+            off_out,
+            len,
+        }
+    }
+}
+
+impl Operation for Splice {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let (off_in, off_out, len) = (self.off_in, self.off_out, self.len);
+        match (self.fd_in.kind(), self.fd_out.kind()) {
+            (FdKind::Fd(i), FdKind::Fd(o)) => opcode::Splice::new(*i, off_in, *o, off_out, len),
+            (FdKind::Fd(i), FdKind::Fixed(o)) => opcode::Splice::new(*i, off_in, *o, off_out, len),
+            (FdKind::Fixed(i), FdKind::Fd(o)) => opcode::Splice::new(*i, off_in, *o, off_out, len),
+            (FdKind::Fixed(i), FdKind::Fixed(o)) => {
+                opcode::Splice::new(*i, off_in, *o, off_out, len)
+            }
+        }
+        .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl Singleshot for Splice {
+    type Output = io::Result<usize>;
+
+    fn complete(self, result: CQEResult) -> Self::Output {
+        result.result.map(|n| n as usize)
+    }
+}
+
+/// Duplicate data between two pipes without consuming it.
+pub(crate) struct Tee {
+    fd_in: NornFd,
+    fd_out: NornFd,
+    len: u32,
+}
+
+impl std::fmt::Debug for Tee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tee").field("len", &self.len).finish()
+    }
+}
+
+impl Tee {
+    pub(crate) fn new(fd_in: NornFd, fd_out: NornFd, len: u32) -> Self {
+        Self { fd_in, fd_out, len }
+    }
+}
+
+impl Operation for Tee {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        let len = self.len;
+        match (self.fd_in.kind(), self.fd_out.kind()) {
+            (FdKind::Fd(i), FdKind::Fd(o)) => opcode::Tee::new(*i, *o, len),
+            (FdKind::Fd(i), FdKind::Fixed(o)) => opcode::Tee::new(*i, *o, len),
+            (FdKind::Fixed(i), FdKind::Fd(o)) => opcode::Tee::new(*i, *o, len),
+            (FdKind::Fixed(i), FdKind::Fixed(o)) => opcode::Tee::new(*i, *o, len),
+        }
+        .build()
+    }
+
+    fn cleanup(&mut self, _: CQEResult) {}
+}
+
+impl Singleshot for Tee {
+    type Output = io::Result<usize>;
+
+    fn complete(self, result: CQEResult) -> Self::Output {
+        result.result.map(|n| n as usize)
+    }
+}
@@ -0,0 +1,67 @@
+//! Timers backed by io_uring timeout operations.
+use std::future::{poll_fn, Future};
+use std::io;
+use std::pin::{pin, Pin};
+use std::task::Poll;
+use std::time::Duration;
+
+use io_uring::{opcode, types};
+
+use crate::operation::{Op, Operation, Singleshot};
+use crate::Handle;
+
+/// An operation which completes once a duration has elapsed.
+///
+/// Dropping the operation before it completes cancels the timeout.
+pub(crate) struct Sleep {
+    ts: types::Timespec,
+}
+
+impl Operation for Sleep {
+    fn configure(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        opcode::Timeout::new(&self.ts as *const _).build()
+    }
+
+    fn cleanup(&mut self, _: crate::operation::CQEResult) {}
+}
+
+impl Singleshot for Sleep {
+    type Output = io::Result<()>;
+
+    fn complete(self, result: crate::operation::CQEResult) -> Self::Output {
+        match result.result {
+            Err(err) if err.raw_os_error() == Some(libc::ETIME) => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+}
+
+/// Returns an operation which completes once `duration` has elapsed.
+pub(crate) fn sleep(duration: Duration) -> Op<Sleep> {
+    let ts = types::Timespec::from(duration);
+    Handle::current().submit(Sleep { ts })
+}
+
+/// Runs `future` to completion, failing with [`io::ErrorKind::TimedOut`] if it
+/// does not complete within `duration`.
+///
+/// The future is dropped once the timeout elapses, cancelling any operations
+/// it has in flight.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> io::Result<F::Output> {
+    let mut future = pin!(future);
+    let mut sleep = pin!(sleep(duration));
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "operation timed out",
+            ))),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    })
+    .await
+}
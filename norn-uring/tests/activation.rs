@@ -0,0 +1,139 @@
+use std::os::fd::AsFd;
+use std::process::Command;
+
+use norn_uring::net::activation::{Handoff, ListenFds};
+use norn_uring::net::{TcpListener, TcpSocket, UdpSocket};
+
+mod util;
+
+const STAGE: &str = "NORN_ACTIVATION_STAGE";
+
+// Runs `handoff_child` in a new process, which re-execs itself passing its
+// sockets to the next stage.
+#[test]
+fn handoff() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new(std::env::current_exe()?)
+        .args([
+            "--exact",
+            "handoff_child",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(STAGE, "exec")
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn handoff_child() -> Result<(), Box<dyn std::error::Error>> {
+    let stage = match std::env::var(STAGE) {
+        Ok(stage) => stage,
+        // Only run as part of `handoff`.
+        Err(_) => return Ok(()),
+    };
+    util::with_test_env(|| async move {
+        if stage == "exec" {
+            let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+            let udp = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+            // Connections made during the handoff wait in the backlog.
+            let _client = TcpSocket::connect(listener.local_addr()?).await?;
+
+            let mut handoff = Handoff::new();
+            handoff.add("web", listener.as_fd())?;
+            handoff.add("dns", udp.as_fd())?;
+            handoff.add("extra", udp.as_fd())?;
+            assert!(handoff.add("a:b", udp.as_fd()).is_err());
+
+            let mut command = Command::new(std::env::current_exe()?);
+            command
+                .args([
+                    "--exact",
+                    "handoff_child",
+                    "--nocapture",
+                    "--test-threads=1",
+                ])
+                .env(STAGE, "adopt");
+            return Err(handoff.exec(&mut command).into());
+        }
+
+        let mut fds = ListenFds::from_env()?;
+        assert_eq!(3, fds.len());
+        // The sockets are only adopted once.
+        assert!(ListenFds::from_env()?.is_empty());
+        // Safety: tests run on a single thread in this process.
+        unsafe { ListenFds::clear_env() };
+        assert!(std::env::var_os("LISTEN_FDS").is_none());
+
+        // The connection made before the handoff is accepted by the new process.
+        let listener = fds.take_tcp_listener("web")?;
+        listener.accept().await?;
+
+        let udp = fds.take_udp_socket("dns")?;
+        assert!(udp.local_addr()?.port() > 0);
+
+        // A datagram socket is not a listener.
+        let extra = fds.take("extra").unwrap();
+        assert_eq!("extra", extra.name());
+        assert!(extra.into_tcp_listener().is_err());
+
+        let err = fds.take_tcp_listener("web").unwrap_err();
+        assert_eq!(std::io::ErrorKind::NotFound, err.kind());
+        assert!(fds.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn no_listen_fds() -> Result<(), Box<dyn std::error::Error>> {
+    let fds = ListenFds::from_env()?;
+    assert!(fds.is_empty());
+    Ok(())
+}
+
+// Runs `missing_fds_child` in a new process, as it sets the activation
+// variables for its own pid.
+#[test]
+fn missing_fds() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new(std::env::current_exe()?)
+        .args([
+            "--exact",
+            "missing_fds_child",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(STAGE, "missing")
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn missing_fds_child() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os(STAGE).is_none() {
+        // Only run as part of `missing_fds`.
+        return Ok(());
+    }
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    // Safety: tests run on a single thread in this process.
+    unsafe {
+        std::env::set_var("LISTEN_PID", std::process::id().to_string());
+        // Not all of these file descriptors are open.
+        std::env::set_var("LISTEN_FDS", "500");
+    }
+
+    let err = ListenFds::from_env().unwrap_err();
+    assert_eq!(Some(libc::EBADF), err.raw_os_error());
+    // The open file descriptors were not closed, and adopting can be retried.
+    assert!(socket.local_addr().is_ok());
+    assert!(ListenFds::from_env().is_err());
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+
+use futures_util::{FutureExt, StreamExt};
+use norn_uring::fs;
+
+mod util;
+
+#[test]
+fn create_remove_dir() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let path = dir.join("a");
+        fs::create_dir(&path).await?;
+        assert!(path.is_dir());
+
+        // remove_dir must not unlink regular files.
+        let file = dir.join("file");
+        std::fs::write(&file, b"hello")?;
+        let err = fs::remove_dir(&file).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+
+        fs::remove_dir(&path).await?;
+        assert!(!path.exists());
+        Ok(())
+    })
+}
+
+#[test]
+fn create_remove_dir_all() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let root = dir.join("a");
+        let nested = root.join("b").join("c");
+        fs::create_dir_all(&nested).await?;
+        assert!(nested.is_dir());
+        // Creating an existing tree is not an error.
+        fs::create_dir_all(&nested).await?;
+
+        std::fs::write(nested.join("file"), b"hello")?;
+        std::os::unix::fs::symlink(dir.join("outside"), root.join("link"))?;
+        fs::remove_dir_all(&root).await?;
+        assert!(!root.exists());
+        Ok(())
+    })
+}
+
+#[test]
+fn rename() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let a = dir.join("a");
+        let b = dir.join("b");
+        std::fs::write(&a, b"a")?;
+        std::fs::write(&b, b"b")?;
+
+        let err = fs::rename_noreplace(&a, &b).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        fs::rename_exchange(&a, &b).await?;
+        assert_eq!(std::fs::read(&a)?, b"b");
+        assert_eq!(std::fs::read(&b)?, b"a");
+
+        fs::rename(&a, &b).await?;
+        assert!(!a.exists());
+        assert_eq!(std::fs::read(&b)?, b"b");
+        Ok(())
+    })
+}
+
+#[test]
+fn links() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let original = dir.join("original");
+        std::fs::write(&original, b"hello")?;
+
+        let hard = dir.join("hard");
+        fs::hard_link(&original, &hard).await?;
+        assert_eq!(std::fs::read(&hard)?, b"hello");
+
+        let soft = dir.join("soft");
+        fs::symlink(&original, &soft).await?;
+        assert_eq!(std::fs::read_link(&soft)?, original);
+        Ok(())
+    })
+}
+
+#[test]
+fn dir_relative_ops() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let tmp = util::ThreadNameTestDir::new();
+        let dir = fs::Dir::open(&*tmp).await?;
+
+        dir.create_dir("sub").await?;
+        assert!(tmp.join("sub").is_dir());
+
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true);
+        let file = dir.open_file("sub/file", &opts).await?;
+        let (res, _) = file.write_at(b"hello".to_vec(), 0).await;
+        res?;
+        file.close().await?;
+        assert_eq!(std::fs::read(tmp.join("sub/file"))?, b"hello");
+
+        let sub = dir.open_dir("sub").await?;
+        assert_eq!(sub.metadata("file").await?.len(), 5);
+        sub.rename("file", "renamed").await?;
+        sub.rename_into("renamed", &dir, "moved").await?;
+        assert!(tmp.join("moved").is_file());
+        sub.close().await?;
+
+        dir.remove_file("moved").await?;
+        dir.remove_dir("sub").await?;
+        dir.sync().await?;
+        assert!(!tmp.join("sub").exists());
+        dir.close().await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn read_dir() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let tmp = util::ThreadNameTestDir::new();
+        std::fs::write(tmp.join("file"), b"hello")?;
+        std::fs::create_dir(tmp.join("sub"))?;
+        std::os::unix::fs::symlink(tmp.join("file"), tmp.join("link"))?;
+
+        let mut entries = vec![];
+        let mut stream = fs::read_dir(&*tmp).await?;
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            let file_type = entry.file_type().await?;
+            entries.push((entry.file_name().into_string().unwrap(), file_type));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let names: Vec<_> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["file", "link", "sub"]);
+        assert!(entries[0].1.is_file());
+        assert!(entries[1].1.is_symlink());
+        assert!(entries[2].1.is_dir());
+        Ok(())
+    })
+}
+
+#[test]
+fn read_dir_yields() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let tmp = util::ThreadNameTestDir::new();
+        // Enough entries for several `getdents64` batches.
+        for i in 0..500 {
+            std::fs::write(tmp.join(format!("{i:0>40}")), b"")?;
+        }
+
+        let mut stream = fs::read_dir(&*tmp).await?;
+        let mut count = 0;
+        let mut yields = 0;
+        loop {
+            match stream.next().now_or_never() {
+                Some(Some(entry)) => {
+                    entry?;
+                    count += 1;
+                }
+                Some(None) => break,
+                None => yields += 1,
+            }
+        }
+        assert_eq!(500, count);
+        // The stream yields between batches.
+        assert!(yields > 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn non_utf8_paths() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let tmp = util::ThreadNameTestDir::new();
+        let name = OsStr::from_bytes(b"not-utf8-\xff\xfe");
+        let path = tmp.join(name);
+
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true);
+        let file = opts.open(&path).await?;
+        let (res, _) = file.write_at(b"hello".to_vec(), 0).await;
+        res?;
+        file.close().await?;
+        assert_eq!(fs::metadata(&path).await?.len(), 5);
+
+        let mut stream = fs::read_dir(&*tmp).await?;
+        let entry = stream.next().await.unwrap()?;
+        assert_eq!(entry.file_name(), name);
+
+        let dir = tmp.join(OsStr::from_bytes(b"dir-\xff"));
+        fs::create_dir(&dir).await?;
+        fs::rename(&path, dir.join(name)).await?;
+        fs::remove_file(dir.join(name)).await?;
+        fs::remove_dir(&dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn open_resolve() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let tmp = util::ThreadNameTestDir::new();
+        std::fs::create_dir(tmp.join("root"))?;
+        std::fs::write(tmp.join("root/inside"), b"inside")?;
+        std::fs::write(tmp.join("outside"), b"outside")?;
+        std::os::unix::fs::symlink("../outside", tmp.join("root/escape"))?;
+        std::os::unix::fs::symlink("inside", tmp.join("root/link"))?;
+        let root = fs::Dir::open(tmp.join("root")).await?;
+
+        let mut beneath = fs::OpenOptions::new();
+        beneath.read(true).resolve_beneath(true);
+        root.open_file("inside", &beneath).await?.close().await?;
+        root.open_file("link", &beneath).await?.close().await?;
+        for path in ["../outside", "escape"] {
+            let err = root.open_file(path, &beneath).await.unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EXDEV));
+        }
+        let absolute = tmp.join("root/inside");
+        let err = root.open_file(&absolute, &beneath).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EXDEV));
+
+        let mut no_symlinks = fs::OpenOptions::new();
+        no_symlinks.read(true).resolve_no_symlinks(true);
+        root.open_file("inside", &no_symlinks)
+            .await?
+            .close()
+            .await?;
+        let err = root.open_file("link", &no_symlinks).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ELOOP));
+        Ok(())
+    })
+}
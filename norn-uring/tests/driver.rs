@@ -0,0 +1,64 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::task::Poll;
+
+use bytes::{Bytes, BytesMut};
+use norn_executor::spawn;
+use norn_uring::net::UnixStream;
+
+/// Yields to the executor once, waking itself.
+async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// Rings set up with `IORING_SETUP_DEFER_TASKRUN` only post completions when
+/// the driver asks for events, which it must also do when it does not block.
+#[test]
+fn defer_taskrun_completes_without_parking() -> Result<(), Box<dyn std::error::Error>> {
+    const MAX_SPINS: usize = 10_000;
+    let mut builder = io_uring::IoUring::builder();
+    builder.setup_single_issuer().setup_defer_taskrun();
+    let driver = norn_uring::Driver::new(builder, 32)?;
+    let mut ex = norn_executor::LocalExecutor::new(driver);
+
+    ex.block_on(async {
+        // A runnable task keeps the executor from blocking in the driver. It
+        // gives up eventually, so completions which are never posted fail the
+        // assertion below rather than hanging the test.
+        let done = Rc::new(Cell::new(false));
+        let spins = Rc::new(Cell::new(0));
+        spawn({
+            let (done, spins) = (done.clone(), spins.clone());
+            async move {
+                while !done.get() && spins.get() < MAX_SPINS {
+                    spins.set(spins.get() + 1);
+                    yield_now().await;
+                }
+            }
+        })
+        .detach();
+
+        // The recv is armed before data arrives, so it completes from task work.
+        let (a, b) = UnixStream::pair()?;
+        let (recv, send) = futures_util::future::join(
+            b.recv(BytesMut::with_capacity(16)),
+            a.send(Bytes::from_static(b"ping")),
+        )
+        .await;
+        assert_eq!(4, send.0?);
+        assert_eq!(4, recv.0?);
+        done.set(true);
+
+        assert!(spins.get() < MAX_SPINS, "completion was not reaped");
+        Ok(())
+    })
+}
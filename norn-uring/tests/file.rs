@@ -1,3 +1,9 @@
+use std::io::{self, SeekFrom};
+use std::pin::pin;
+use std::time::{Duration, SystemTime};
+
+use norn_uring::buf::AlignedBuf;
+use norn_uring::fixedbuf::FixedBufPool;
 use norn_uring::fs;
 
 mod util;
@@ -37,3 +43,259 @@ fn read_write() -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     })
 }
+
+#[test]
+fn read_write_fixed() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let path = dir.join("testfile");
+        let mut opts = fs::OpenOptions::new();
+        opts.create(true).write(true).read(true);
+
+        let pool = FixedBufPool::builder().buf_cnt(2).buf_len(4096).build()?;
+        let file = opts.open(path).await?;
+        let mut buf = pool.try_next().unwrap();
+        buf.put_slice(b"hello world");
+        let (res, buf) = file.write_fixed_at(buf, 0).await;
+        assert_eq!(res?, 11);
+        drop(buf);
+
+        let buf = pool.try_next().unwrap();
+        let (res, buf) = file.read_fixed_at(buf, 0).await;
+        assert_eq!(res?, 11);
+        assert_eq!(&buf[..], b"hello world");
+        Ok(())
+    })
+}
+
+#[test]
+fn fixed_pool_size_overflow() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let err = FixedBufPool::builder()
+            .buf_cnt(2)
+            .buf_len(usize::MAX)
+            .build()
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        Ok(())
+    })
+}
+
+#[test]
+fn fixed_pool_outlives_executor() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = util::with_test_env(|| async { Ok(FixedBufPool::builder().build()?) })?;
+    // Unregistering the buffers does not need the driver context.
+    drop(pool);
+    Ok(())
+}
+
+#[test]
+fn fixed_pool_exhaustion() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let pool = FixedBufPool::builder().buf_cnt(1).build()?;
+        let b1 = pool.try_next().unwrap();
+        assert!(pool.try_next().is_none());
+        assert_eq!(0, pool.available());
+
+        // Only one set of buffers can be registered at a time.
+        assert!(FixedBufPool::builder().build().is_err());
+
+        let waiter = norn_executor::spawn({
+            let pool = pool.clone();
+            async move { pool.next().await.len() }
+        });
+        drop(b1);
+        assert_eq!(0, waiter.await?);
+        Ok(())
+    })
+}
+
+#[test]
+fn readv_writev() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let path = dir.join("testfile");
+        let mut opts = fs::OpenOptions::new();
+        opts.create(true).write(true).read(true);
+
+        let file = opts.open(path).await?;
+        let bufs = [&b"header"[..], &b"payload"[..], &b"footer"[..]];
+        let (res, _) = file.writev_at(bufs, 0).await;
+        assert_eq!(res?, 19);
+
+        let bufs = vec![Vec::with_capacity(6), Vec::with_capacity(20)];
+        let (res, bufs) = file.readv_at(bufs, 0).await;
+        assert_eq!(res?, 19);
+        assert_eq!(bufs[0], b"header");
+        assert_eq!(bufs[1], b"payloadfooter");
+        Ok(())
+    })
+}
+
+#[test]
+fn read_exact_write_all() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let path = dir.join("testfile");
+        let mut opts = fs::OpenOptions::new();
+        opts.create(true).write(true).read(true);
+
+        let file = opts.open(path).await?;
+        let (res, _) = file.write_all_at(&b"hello world"[..], 0).await;
+        res?;
+
+        let (res, buf) = file.read_exact_at(Vec::with_capacity(5), 6).await;
+        res?;
+        assert_eq!(buf, b"world");
+
+        // Reading past the end of the file hands back the partial read.
+        let (res, buf) = file.read_exact_at(Vec::with_capacity(16), 0).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(buf, b"hello world");
+        Ok(())
+    })
+}
+
+#[test]
+fn metadata() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let path = dir.join("testfile");
+        let mut opts = fs::OpenOptions::new();
+        opts.create(true).write(true);
+
+        let file = opts.open(&path).await?;
+        file.write_all_at(&b"hello world"[..], 0).await.0?;
+
+        let meta = file.metadata().await?;
+        assert!(meta.is_file());
+        assert_eq!(meta.len(), 11);
+        assert!(meta.block_size() > 0);
+        let modified = meta.modified()?;
+        assert!(SystemTime::now().duration_since(modified)? < Duration::from_secs(60));
+
+        let meta = fs::metadata(&path).await?;
+        assert_eq!(meta.len(), 11);
+        assert!(fs::metadata(&*dir).await?.is_dir());
+
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&path, &link)?;
+        assert!(fs::metadata(&link).await?.is_file());
+        assert!(fs::symlink_metadata(&link).await?.is_symlink());
+
+        let err = fs::metadata(dir.join("missing")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        Ok(())
+    })
+}
+
+#[test]
+fn file_stream() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let mut opts = fs::OpenOptions::new();
+        opts.read(true).write(true).create(true);
+        let file = opts.open(dir.join("testfile")).await?;
+        // Use a small buffer so reads and writes span several operations.
+        let mut stream = pin!(fs::FileStream::with_capacity(4, file));
+
+        stream.write_all(b"hello\nworld\n").await?;
+        stream.flush().await?;
+        assert_eq!(stream.position(), 12);
+
+        assert_eq!(stream.seek(SeekFrom::Start(0)).await?, 0);
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        assert_eq!(line, "hello\n");
+
+        // Overwrite "world" and read back the rest of the file.
+        stream.write_all(b"there").await?;
+        assert_eq!(stream.seek(SeekFrom::Current(-5)).await?, 6);
+        let mut rest = String::new();
+        stream.read_to_string(&mut rest).await?;
+        assert_eq!(rest, "there\n");
+
+        assert_eq!(stream.seek(SeekFrom::End(-3)).await?, 9);
+        let mut buf = [0; 2];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"re");
+        assert!(stream.seek(SeekFrom::Current(-20)).await.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn file_stream_futures_io() -> Result<(), Box<dyn std::error::Error>> {
+    use futures_util::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let mut opts = fs::OpenOptions::new();
+        opts.read(true).write(true).create(true);
+        let file = opts.open(dir.join("testfile")).await?;
+        let mut stream = pin!(file.into_stream());
+
+        stream.write_all(b"hello world").await?;
+        stream.close().await?;
+        stream.seek(SeekFrom::Start(6)).await?;
+        let mut buf = vec![];
+        stream.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"world");
+        Ok(())
+    })
+}
+
+#[test]
+fn direct_io_aligned_buf() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let mut opts = fs::OpenOptions::new();
+        opts.read(true).write(true).create(true).direct(true);
+        let file = opts.open(dir.join("testfile")).await?;
+
+        let metadata = file.metadata().await?;
+        let mut buf = AlignedBuf::for_direct_io(4096, &metadata)?;
+        assert_eq!(buf.as_ptr() as usize % buf.alignment(), 0);
+        buf.put_slice(b"hello world");
+        buf.set_len(buf.capacity());
+        let (res, buf) = file.write_at(buf, 0).await;
+        assert_eq!(res?, buf.capacity());
+
+        let read_buf = AlignedBuf::for_direct_io(4096, &metadata)?;
+        let (res, read_buf) = file.read_at(read_buf, 0).await;
+        assert_eq!(res?, buf.capacity());
+        assert_eq!(&read_buf[..11], b"hello world");
+
+        if cfg!(debug_assertions) {
+            let (res, _) = file.write_at(b"unaligned".to_vec(), 0).await;
+            let err = res.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(err.to_string().contains("O_DIRECT write_at"), "{err}");
+
+            let (res, read_buf) = file.read_at(read_buf, 1).await;
+            let err = res.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(err.to_string().contains("file offset"), "{err}");
+
+            // The vectored, fixed and looping variants are checked too.
+            let (res, _) = file.writev_at(vec![b"unaligned".to_vec()], 0).await;
+            let err = res.unwrap_err();
+            assert!(err.to_string().contains("O_DIRECT writev_at"), "{err}");
+
+            let (res, _) = file.readv_at(vec![read_buf], 1).await;
+            let err = res.unwrap_err();
+            assert!(err.to_string().contains("O_DIRECT readv_at"), "{err}");
+
+            let (res, _) = file.write_all_at(b"unaligned".to_vec(), 0).await;
+            assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+            let pool = FixedBufPool::builder().buf_cnt(1).buf_len(4096).build()?;
+            let (res, _) = file.read_fixed_at(pool.try_next().unwrap(), 1).await;
+            let err = res.unwrap_err();
+            assert!(err.to_string().contains("O_DIRECT read_fixed_at"), "{err}");
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,152 @@
+use std::pin::pin;
+
+use futures_util::StreamExt;
+use norn_executor::spawn;
+use norn_uring::net::{TcpListener, TcpSocket};
+use norn_uring::{fs, pipe};
+
+mod util;
+
+#[test]
+fn read_write_tee() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let (reader, writer) = pipe::pipe()?;
+        let (tee_reader, tee_writer) = pipe::pipe()?;
+
+        let (res, _) = writer.write(&b"hello"[..]).await;
+        assert_eq!(res?, 5);
+        assert_eq!(reader.tee(&tee_writer, 5).await?, 5);
+
+        // The teed data is still available on the original pipe.
+        let (res, buf) = reader.read(Vec::with_capacity(16)).await;
+        assert_eq!(res?, 5);
+        assert_eq!(buf, b"hello");
+        let (res, buf) = tee_reader.read(Vec::with_capacity(16)).await;
+        assert_eq!(res?, 5);
+        assert_eq!(buf, b"hello");
+
+        writer.close().await?;
+        let (res, _) = reader.read(Vec::with_capacity(16)).await;
+        assert_eq!(res?, 0);
+        Ok(())
+    })
+}
+
+#[test]
+fn file_splice() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        std::fs::write(dir.join("src"), b"hello world")?;
+        let src = fs::File::open(dir.join("src")).await?;
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true);
+        let dst = opts.open(dir.join("dst")).await?;
+
+        let (reader, writer) = pipe::pipe()?;
+        assert_eq!(src.splice_to(6, &writer, 64).await?, 5);
+        assert_eq!(dst.splice_from(&reader, 2, 5).await?, 5);
+        assert_eq!(std::fs::read(dir.join("dst"))?, b"\0\0world");
+        Ok(())
+    })
+}
+
+#[test]
+fn copy_file_to_file() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        // Larger than a single pipe chunk.
+        let data: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+        std::fs::write(dir.join("src"), &data)?;
+        let src = fs::File::open(dir.join("src")).await?;
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true);
+        let dst = opts.open(dir.join("dst")).await?;
+
+        let (res, n) = pipe::copy(&src, None, &dst, None, 150_000).await;
+        res?;
+        assert_eq!(n, 150_000);
+        // The file positions were advanced, so the remainder is appended.
+        let (res, n) = pipe::copy(&src, None, &dst, None, u64::MAX).await;
+        res?;
+        assert_eq!(n, 50_000);
+        assert_eq!(std::fs::read(dir.join("dst"))?, data);
+        Ok(())
+    })
+}
+
+#[test]
+fn copy_file_offsets() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let data: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+        std::fs::write(dir.join("src"), &data)?;
+        let src = fs::File::open(dir.join("src")).await?;
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true);
+        let dst = opts.open(dir.join("dst")).await?;
+
+        // Copy the second half first, then the first half, in several chunks each.
+        let (res, n) = pipe::copy(&src, Some(100_000), &dst, Some(100_000), u64::MAX).await;
+        res?;
+        assert_eq!(n, 100_000);
+        let (res, n) = pipe::copy(&src, Some(0), &dst, Some(0), 100_000).await;
+        res?;
+        assert_eq!(n, 100_000);
+        assert_eq!(std::fs::read(dir.join("dst"))?, data);
+        Ok(())
+    })
+}
+
+#[test]
+fn copy_partial_on_error() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        std::fs::write(dir.join("src"), [1u8; 1000])?;
+        let src = fs::File::open(dir.join("src")).await?;
+        let (reader, writer) = pipe::pipe()?;
+        reader.close().await?;
+
+        // Writing to a pipe without a reader fails, no bytes are copied.
+        let (res, n) = pipe::copy(&src, None, &writer, None, u64::MAX).await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EPIPE));
+        assert_eq!(n, 0);
+        Ok(())
+    })
+}
+
+#[test]
+fn copy_file_to_socket() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let data: Vec<u8> = (0..100_000).map(|i| i as u8).collect();
+        std::fs::write(dir.join("src"), &data)?;
+
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let server = spawn(async move {
+            let mut incoming = pin!(listener.incoming());
+            let conn = incoming.next().await.unwrap()?;
+            let (reader, writer) = pipe::pipe()?;
+            let mut received = vec![];
+            loop {
+                let n = conn.splice_to(&writer, 4096).await?;
+                if n == 0 {
+                    break;
+                }
+                let (res, buf) = reader.read(Vec::with_capacity(n)).await;
+                assert_eq!(res?, n);
+                received.extend_from_slice(&buf);
+            }
+            std::io::Result::Ok(received)
+        });
+
+        let src = fs::File::open(dir.join("src")).await?;
+        let conn = TcpSocket::connect(addr).await?;
+        let (res, n) = pipe::copy(&src, None, &conn, None, u64::MAX).await;
+        res?;
+        assert_eq!(n, data.len() as u64);
+        conn.close().await?;
+        assert_eq!(server.await??, data);
+        Ok(())
+    })
+}
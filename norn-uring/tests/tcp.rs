@@ -1,10 +1,15 @@
-use std::io;
+use std::io::{self, Write};
 use std::net::SocketAddr;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, IntoRawFd};
 use std::pin::pin;
+use std::time::Duration;
 
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
 use norn_executor::spawn;
-use norn_uring::net::{TcpListener, TcpSocket};
+use norn_uring::bufring::BufRing;
+use norn_uring::fixedbuf::FixedBufPool;
+use norn_uring::net::cmsg::ControlMessageBuf;
+use norn_uring::net::{AcceptOptions, ReuniteError, TcpListener, TcpSocket, TcpSocketBuilder};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 mod util;
@@ -60,6 +65,536 @@ fn echo() -> Result<(), Box<dyn std::error::Error>> {
     })
 }
 
+#[test]
+fn echo_fixed() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let server = EchoServer::new().await?;
+
+        let addr = server.local_addr()?;
+        spawn(server.run()).detach();
+        let conn = TcpSocket::connect(addr).await?;
+        let pool = FixedBufPool::builder().buf_cnt(2).build()?;
+
+        let mut buf = pool.next().await;
+        buf.put_slice(b"hello");
+        let (res, buf) = conn.send_fixed(buf).await;
+        assert_eq!(5, res?);
+        drop(buf);
+
+        let mut received = vec![];
+        while received.len() < 5 {
+            let (res, buf) = conn.recv_fixed(pool.next().await).await;
+            let n = res?;
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(b"hello", &received[..]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn echo_zc() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let server = EchoServer::new().await?;
+
+        let addr = server.local_addr()?;
+        spawn(server.run()).detach();
+        let conn = TcpSocket::connect(addr).await?;
+
+        let data: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+        let mut buf = data.clone();
+        while !buf.is_empty() {
+            let (res, b) = conn.send_zc(buf).await;
+            buf = b[res?..].to_vec();
+        }
+
+        let mut received = vec![];
+        while received.len() < data.len() {
+            let (res, buf) = conn.recv(Vec::with_capacity(64 * 1024)).await;
+            let n = res?;
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(data, received);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn echo_msg_zc() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let server = EchoServer::new().await?;
+
+        let addr = server.local_addr()?;
+        spawn(server.run()).detach();
+        let conn = TcpSocket::connect(addr).await?;
+
+        let control = ControlMessageBuf::new();
+        let bufs = vec![b"hello ".to_vec(), b"world".to_vec()];
+        let (res, _) = conn.send_msg_zc(bufs, &control).await;
+        assert_eq!(11, res?);
+
+        let mut received = vec![];
+        while received.len() < 11 {
+            let (res, buf) = conn.recv(Vec::with_capacity(64)).await;
+            let n = res?;
+            assert!(n > 0);
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(b"hello world", &received[..]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn echo_recv_multi() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let server = EchoServer::new().await?;
+
+        let addr = server.local_addr()?;
+        spawn(server.run()).detach();
+        let conn = TcpSocket::connect(addr).await?;
+
+        // A small ring forces the kernel to run out of buffers, which requires the
+        // receive to be re-armed.
+        let ring = BufRing::builder(3).buf_cnt(2).buf_len(4096).build()?;
+        let data: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+        let mut received = vec![];
+        let mut stream = pin!(conn.recv_multi(&ring));
+        let mut sent = 0;
+        for chunk in data.chunks(8 * 1024) {
+            let mut buf = chunk.to_vec();
+            while !buf.is_empty() {
+                let (res, b) = conn.send(buf).await;
+                buf = b[res?..].to_vec();
+            }
+            sent += chunk.len();
+            while received.len() < sent {
+                let buf = stream.next().await.unwrap()?;
+                received.extend_from_slice(&buf[..]);
+            }
+        }
+        assert_eq!(data, received);
+        assert_eq!(ring.buf_count(), ring.available());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn completion_stream_empty_write() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let ring = BufRing::builder(5).buf_cnt(4).buf_len(4096).build()?;
+        let conn = TcpSocket::connect(listener.local_addr()?).await?;
+        let (socket, _) = listener.accept().await?;
+
+        let mut writer = pin!(conn.into_completion_stream(&ring));
+        assert_eq!(0, writer.write(&[]).await?);
+        writer.write_all(b"hello").await?;
+        assert_eq!(0, writer.write(&[]).await?);
+        writer.shutdown().await?;
+
+        let mut reader = pin!(socket.into_completion_stream(&ring));
+        let mut received = vec![];
+        reader.read_to_end(&mut received).await?;
+        assert_eq!(b"hello", &received[..]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn completion_stream_write_without_flush() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        // The peer replies once it receives the request, which is not flushed. It
+        // gives up after a while, so a request that is never sent fails the test.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || -> io::Result<()> {
+            let (mut socket, _) = listener.accept()?;
+            socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+            let mut request = [0; 4];
+            std::io::Read::read_exact(&mut socket, &mut request)?;
+            assert_eq!(b"ping", &request);
+            socket.write_all(b"pong")
+        });
+
+        let ring = BufRing::builder(5).buf_cnt(4).buf_len(4096).build()?;
+        let conn = TcpSocket::connect(addr).await?;
+        let mut stream = pin!(conn.into_completion_stream(&ring));
+        stream.write_all(b"ping").await?;
+        let mut reply = [0; 4];
+        let res = stream.read_exact(&mut reply).await;
+        server.join().unwrap()?;
+        res?;
+        assert_eq!(b"pong", &reply);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn echo_completion_stream() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let ring = BufRing::builder(5).buf_cnt(4).buf_len(4096).build()?;
+
+        // Echo using a completion based stream on both ends, sharing the ring.
+        let server_ring = ring.clone();
+        let server = spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            let (reader, writer) = socket.into_completion_stream(&server_ring).owned_split();
+            let mut reader = pin!(reader);
+            let mut writer = pin!(writer);
+            tokio::io::copy(&mut reader, &mut writer).await?;
+            writer.shutdown().await?;
+            Ok::<_, io::Error>(())
+        });
+
+        let conn = TcpSocket::connect(addr).await?;
+        let (reader, writer) = conn.into_completion_stream(&ring).owned_split();
+
+        // More data than the writer buffers while a send is in flight.
+        let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let expected = data.clone();
+        let client = spawn(async move {
+            let mut writer = pin!(writer);
+            for chunk in data.chunks(10_000) {
+                writer.write_all(chunk).await?;
+            }
+            writer.shutdown().await?;
+            Ok::<_, io::Error>(())
+        });
+
+        let mut reader = pin!(reader);
+        let mut received = vec![];
+        reader.read_to_end(&mut received).await?;
+        client.await??;
+        server.await??;
+        assert_eq!(expected.len(), received.len());
+        assert!(expected == received);
+        assert_eq!(ring.buf_count(), ring.available());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn split_reunite() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let server = EchoServer::new().await?;
+
+        let addr = server.local_addr()?;
+        spawn(server.run()).detach();
+
+        let mut stream = TcpSocket::connect(addr).await?.into_stream();
+        let local = stream.local_addr()?;
+        {
+            let (mut reader, mut writer) = stream.split();
+            assert_eq!(addr.port(), reader.peer_addr()?.port());
+            assert_eq!(local, writer.local_addr()?);
+            writer.write_all(b"hello").await?;
+            let mut buf = [0; 5];
+            reader.read_exact(&mut buf).await?;
+            assert_eq!(b"hello", &buf);
+        }
+
+        // Halves of different streams can't be reunited.
+        let other = TcpSocket::connect(addr).await?.into_stream();
+        let (reader, writer) = TcpSocket::connect(addr).await?.into_stream().owned_split();
+        let (other_reader, other_writer) = other.owned_split();
+        let ReuniteError(reader, other_writer) = reader.reunite(other_writer).unwrap_err();
+        drop(other_writer);
+        drop(other_reader);
+
+        // The stream is `Unpin`, so it can be used without pinning it.
+        let mut stream = writer.reunite(reader)?;
+        stream.write_all(b"again").await?;
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(b"again", &buf);
+
+        let (reader, writer) = stream.split();
+        writer.shutdown(std::net::Shutdown::Write).await?;
+        assert_eq!(local.ip(), reader.local_addr()?.ip());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn builder_reuse_port() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let first = TcpSocketBuilder::new()
+            .local_addr("127.0.0.1:0".parse()?)
+            .reuse_port(true)
+            .listen(32)
+            .await?;
+        let addr = first.local_addr()?;
+
+        // A second listener can share the port.
+        let second = TcpSocketBuilder::new()
+            .local_addr(addr)
+            .reuse_port(true)
+            .listen(32)
+            .await?;
+        assert_eq!(addr, second.local_addr()?);
+
+        // Without SO_REUSEPORT the port is in use.
+        let err = TcpSocketBuilder::new()
+            .local_addr(addr)
+            .listen(32)
+            .await
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::AddrInUse, err.kind());
+
+        // Listening requires a local address.
+        let err = TcpSocketBuilder::new().listen(32).await.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        // Connect from a chosen local address.
+        let conn = TcpSocketBuilder::new()
+            .local_addr("127.0.0.2:0".parse()?)
+            .connect(addr)
+            .await?;
+        assert_eq!(
+            "127.0.0.2".parse::<std::net::IpAddr>()?,
+            conn.local_addr()?.ip()
+        );
+        assert_eq!(addr, conn.peer_addr()?);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_any_fallback() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+
+        // Nothing listens on a port which has just been released.
+        let closed = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let refused = closed.local_addr()?;
+        closed.close().await?;
+
+        // A refused attempt starts the next one immediately.
+        let conn = TcpSocketBuilder::new()
+            .attempt_delay(Duration::from_secs(60))
+            .connect_any([refused, addr])
+            .await?;
+        assert_eq!(addr, conn.peer_addr()?);
+
+        // If every attempt fails the last error is returned.
+        let err = TcpSocket::connect_any([refused]).await.unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionRefused, err.kind());
+
+        let err = TcpSocket::connect_any([]).await.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_any_timeouts() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+
+        // Once the accept queue of a listener is full, connection attempts stall.
+        let stalled = TcpListener::bind("127.0.0.1:0".parse()?, 0).await?;
+        let stalled_addr = stalled.local_addr()?;
+        let _queued = TcpSocket::connect(stalled_addr).await?;
+
+        // The next attempt starts after the delay and wins.
+        let conn = TcpSocketBuilder::new()
+            .attempt_delay(Duration::from_millis(20))
+            .connect_any([stalled_addr, addr])
+            .await?;
+        assert_eq!(addr, conn.peer_addr()?);
+
+        let err = TcpSocketBuilder::new()
+            .attempt_timeout(Duration::from_millis(50))
+            .connect_any([stalled_addr])
+            .await
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+
+        let err = TcpSocketBuilder::new()
+            .connect_timeout(Duration::from_millis(50))
+            .connect(stalled_addr)
+            .await
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn incoming_bounded() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let options = AcceptOptions::new(2).nodelay(true).keepalive(true);
+        let mut incoming = pin!(listener.incoming_bounded(options));
+
+        let _c1 = TcpSocket::connect(addr).await?;
+        let _c2 = TcpSocket::connect(addr).await?;
+        let (_s1, p1) = incoming.next().await.unwrap()?;
+        let (_s2, _p2) = incoming.next().await.unwrap()?;
+        assert_eq!(2, incoming.active());
+
+        // The limit is reached, new connections wait in the backlog.
+        let c3 = TcpSocket::connect(addr).await?;
+        assert!(incoming.next().now_or_never().is_none());
+
+        // Releasing a permit hands out the third connection, whether it waited in
+        // the backlog or was accepted before the stream paused.
+        drop(p1);
+        assert_eq!(1, incoming.active());
+        let (s3, _p3) = incoming.next().await.unwrap()?;
+        assert_eq!(c3.local_addr()?, s3.peer_addr()?);
+        assert_eq!(2, incoming.active());
+        assert_eq!(0, incoming.queued());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn incoming_bounded_burst() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let addr = listener.local_addr()?;
+        let mut incoming = pin!(listener.incoming_bounded(AcceptOptions::new(1)));
+
+        // The multishot accept takes every connection waiting in the backlog.
+        let mut clients = vec![];
+        for _ in 0..4 {
+            clients.push(TcpSocket::connect(addr).await?);
+        }
+        let mut peers = vec![];
+        for _ in 0..clients.len() {
+            let (socket, permit) = incoming.next().await.unwrap()?;
+            peers.push(socket.peer_addr()?);
+            assert!(incoming.next().now_or_never().is_none());
+            drop(permit);
+        }
+        let expected = clients
+            .iter()
+            .map(|c| c.local_addr())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(expected, peers);
+        // Accepting was paused once, after the first connection, and the
+        // remaining connections were queued.
+        assert_eq!(1, incoming.pauses());
+        assert_eq!(3, incoming.queued_total());
+        assert_eq!(0, incoming.queued());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn socket_options() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let listener = TcpListener::bind("127.0.0.1:0".parse()?, 32).await?;
+        let socket = TcpSocket::connect(listener.local_addr()?).await?;
+
+        socket.set_nodelay(true)?;
+        assert!(socket.nodelay()?);
+
+        socket.set_keepalive_time(Duration::from_secs(30))?;
+        socket.set_keepalive_interval(Duration::from_secs(5))?;
+        socket.set_keepalive_retries(3)?;
+        assert!(socket.keepalive()?);
+        assert_eq!(Duration::from_secs(30), socket.keepalive_time()?);
+        assert_eq!(Duration::from_secs(5), socket.keepalive_interval()?);
+        assert_eq!(3, socket.keepalive_retries()?);
+
+        socket.set_user_timeout(Some(Duration::from_secs(10)))?;
+        assert_eq!(Some(Duration::from_secs(10)), socket.user_timeout()?);
+
+        socket.set_linger(Some(Duration::from_secs(1)))?;
+        assert_eq!(Some(Duration::from_secs(1)), socket.linger()?);
+
+        socket.set_cork(true)?;
+        assert!(socket.cork()?);
+        socket.set_cork(false)?;
+
+        socket.set_notsent_lowat(16 * 1024)?;
+        assert_eq!(16 * 1024, socket.notsent_lowat()?);
+
+        socket.set_congestion(b"reno")?;
+        assert_eq!(b"reno", &socket.congestion()?[..]);
+
+        socket.set_tos(0x10)?;
+        assert_eq!(0x10, socket.tos()?);
+
+        // Setting the mark requires CAP_NET_ADMIN.
+        match socket.set_mark(7) {
+            Ok(()) => assert_eq!(7, socket.mark()?),
+            Err(err) => assert_eq!(io::ErrorKind::PermissionDenied, err.kind()),
+        }
+
+        assert!(socket.take_error()?.is_none());
+
+        let info = socket.tcp_info()?;
+        assert_eq!(TCP_ESTABLISHED, info.state());
+        assert!(info.snd_mss() > 0);
+        assert!(info.pmtu() > 0);
+
+        Ok(())
+    })
+}
+
+// From `include/net/tcp_states.h`.
+const TCP_ESTABLISHED: u8 = 1;
+
+#[test]
+fn std_conversions() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let listener = TcpListener::from_std(std_listener)?;
+        let addr = listener.local_addr()?;
+
+        let client = TcpSocket::connect(addr).await?;
+        let (server, _) = listener.accept().await?;
+
+        // The socket can not be extracted while an operation holds it.
+        let send = client.send(b"ping".to_vec());
+        let client = client.into_std().unwrap_err();
+        drop(send);
+
+        // Extracting the socket does not close it.
+        let mut std_client = client.into_std().unwrap();
+        std_client.write_all(b"ping")?;
+        let mut server = pin!(server.into_stream());
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(b"ping", &buf);
+
+        // Round trip through a raw file descriptor.
+        let fd = TcpSocket::from_std(std_client)?.into_raw_fd();
+        let client = unsafe { TcpSocket::from_raw_fd(fd) };
+        assert_eq!(fd, client.as_fd().as_raw_fd());
+        let mut client = pin!(client.into_stream());
+        server.write_all(b"pong").await?;
+        client.read_exact(&mut buf).await?;
+        assert_eq!(b"pong", &buf);
+
+        let std_listener = listener.into_std().unwrap();
+        assert_eq!(addr, std_listener.local_addr()?);
+        Ok(())
+    })
+}
+
 struct EchoServer {
     listener: TcpListener,
 }
@@ -82,7 +617,7 @@ impl EchoServer {
             socket.set_recv_buffer_size(64)?;
             socket.set_send_buffer_size(64)?;
             spawn(async move {
-                let (mut reader, mut writer) = socket.into_stream().owned_split();
+                let (reader, writer) = socket.into_stream().owned_split();
                 let mut reader = pin!(reader);
                 let mut writer = pin!(writer);
                 if let Err(err) = tokio::io::copy(&mut reader, &mut writer).await {
@@ -1,6 +1,11 @@
 use bytes::{Bytes, BytesMut};
 use norn_uring::bufring::BufRing;
-use norn_uring::net::UdpSocket;
+use std::net::IpAddr;
+use std::pin::pin;
+
+use futures_util::StreamExt;
+use norn_uring::net::cmsg::{ControlMessage, ControlMessageBuf};
+use norn_uring::net::{UdpSocket, UdpSocketBuilder};
 
 mod util;
 
@@ -53,6 +58,307 @@ fn send_recv_ring() -> Result<(), Box<dyn std::error::Error>> {
     })
 }
 
+#[test]
+fn send_to_zc() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+
+        let buf = Bytes::from_static(b"hello");
+        let (res, buf) = s1.send_to_zc(buf, s2.local_addr()?).await;
+        assert_eq!(buf.len(), res?);
+
+        let (res, buf) = s2.recv_from(BytesMut::with_capacity(5)).await;
+        let (n, addr) = res?;
+        assert_eq!(s1.local_addr()?, addr);
+        assert_eq!(b"hello", &buf[..n]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn recvmsg_multi() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        // Small enough that the ring runs out of buffers and the receive is re-armed.
+        let ring = BufRing::builder(3).buf_cnt(2).buf_len(1024).build()?;
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        s2.set_recv_pktinfo(true)?;
+        s2.set_recv_timestamp(true)?;
+
+        let mut stream = pin!(s2.recvmsg_multi(&ring));
+        for i in 0..8u8 {
+            for j in 0..4u8 {
+                s1.send_to(vec![i, j], s2.local_addr()?).await.0?;
+            }
+            for j in 0..4u8 {
+                let msg = stream.next().await.unwrap()?;
+                assert_eq!(&[i, j], msg.payload());
+                assert_eq!(Some(s1.local_addr()?), msg.source());
+                assert!(!msg.is_payload_truncated());
+                assert!(!msg.is_control_truncated());
+
+                let mut pktinfo = false;
+                let mut timestamp = false;
+                for cmsg in msg.control_messages() {
+                    match cmsg {
+                        ControlMessage::PacketInfo { addr, .. } => {
+                            assert_eq!(IpAddr::from([127, 0, 0, 1]), addr);
+                            pktinfo = true;
+                        }
+                        ControlMessage::Timestamp(_) => timestamp = true,
+                        _ => {}
+                    }
+                }
+                assert!(pktinfo && timestamp);
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_msg_gso() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+
+        // Send a single message which is split into three datagrams.
+        let mut control = ControlMessageBuf::new();
+        control.push(ControlMessage::SegmentSize(4))?;
+        let bufs = vec![b"aaaa".to_vec(), b"bbbbcccc".to_vec()];
+        let (res, _) = s1.send_msg(bufs, Some(s2.local_addr()?), &control).await;
+        assert_eq!(12, res?);
+
+        for expected in [b"aaaa", b"bbbb", b"cccc"] {
+            let (res, buf) = s2.recv_from(Vec::with_capacity(16)).await;
+            let (n, addr) = res?;
+            assert_eq!(s1.local_addr()?, addr);
+            assert_eq!(expected, &buf[..n]);
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_msg_zc_gso() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+
+        let mut control = ControlMessageBuf::new();
+        control.push(ControlMessage::SegmentSize(4))?;
+        let bufs = vec![b"aaaa".to_vec(), b"bbbbcccc".to_vec()];
+        let (res, bufs) = s1.send_msg_zc(bufs, Some(s2.local_addr()?), &control).await;
+        assert_eq!(12, res?);
+        assert_eq!(b"aaaa", &bufs[0][..]);
+
+        for expected in [b"aaaa", b"bbbb", b"cccc"] {
+            let (res, buf) = s2.recv_from(Vec::with_capacity(16)).await;
+            let (n, addr) = res?;
+            assert_eq!(s1.local_addr()?, addr);
+            assert_eq!(expected, &buf[..n]);
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_recv_msg_control() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        s2.set_recv_tos(true)?;
+        s2.set_recv_pktinfo(true)?;
+
+        let mut control = ControlMessageBuf::new();
+        control.push(ControlMessage::Tos(0x10))?;
+        control.push(ControlMessage::PacketInfo {
+            addr: IpAddr::from([127, 0, 0, 1]),
+            ifindex: 0,
+        })?;
+        // Timestamps can only be received.
+        let timestamp = ControlMessage::Timestamp(std::time::SystemTime::now());
+        assert!(control.push(timestamp).is_err());
+
+        let bufs = vec![b"hello ".to_vec(), b"world".to_vec()];
+        let (res, _) = s1.send_msg(bufs, Some(s2.local_addr()?), &control).await;
+        assert_eq!(11, res?);
+
+        // Scatter the message across two buffers.
+        let bufs = vec![Vec::with_capacity(4), Vec::with_capacity(16)];
+        let (res, bufs) = s2.recv_msg(bufs).await;
+        let info = res?;
+        assert_eq!(11, info.len());
+        assert_eq!(Some(s1.local_addr()?), info.source());
+        assert!(!info.is_truncated());
+        assert_eq!(b"hell", &bufs[0][..]);
+        assert_eq!(b"o world", &bufs[1][..]);
+
+        let cmsgs: Vec<_> = info.control_messages().iter().collect();
+        assert!(cmsgs.contains(&ControlMessage::Tos(0x10)));
+        assert!(cmsgs.iter().any(
+            |cmsg| matches!(cmsg, ControlMessage::PacketInfo { addr, .. } if addr.is_loopback())
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_recv_msg_array() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+
+        let control = ControlMessageBuf::new();
+        let bufs = [Bytes::from_static(b"hello "), Bytes::from_static(b"world")];
+        let (res, _) = s1.send_msg(bufs, Some(s2.local_addr()?), &control).await;
+        assert_eq!(11, res?);
+
+        let bufs = [BytesMut::with_capacity(6), BytesMut::with_capacity(16)];
+        let (res, bufs) = s2.recv_msg(bufs).await;
+        assert_eq!(11, res?.len());
+        assert_eq!(b"hello ", &bufs[0][..]);
+        assert_eq!(b"world", &bufs[1][..]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn recv_pktinfo_dual_stack() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocketBuilder::new()
+            .only_v6(false)
+            .bind("[::]:0".parse()?)
+            .await?;
+        s2.set_recv_pktinfo(true)?;
+
+        let dst = std::net::SocketAddr::from(([127, 0, 0, 1], s2.local_addr()?.port()));
+        let (res, _) = s1.send_to(b"hello".to_vec(), dst).await;
+        assert_eq!(5, res?);
+
+        let (res, _) = s2.recv_msg(vec![Vec::with_capacity(16)]).await;
+        let info = res?;
+        assert!(info.control_messages().iter().any(|cmsg| matches!(
+            cmsg,
+            ControlMessage::PacketInfo { addr, .. } if addr == IpAddr::from([127, 0, 0, 1])
+        )));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn builder() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let s1 = UdpSocketBuilder::new()
+            .reuse_port(true)
+            .bind("127.0.0.1:0".parse()?)
+            .await?;
+        let addr = s1.local_addr()?;
+        let s2 = UdpSocketBuilder::new().reuse_port(true).bind(addr).await?;
+        assert_eq!(addr, s2.local_addr()?);
+
+        // A v6 only socket and a v4 socket can share a port.
+        let v6 = UdpSocketBuilder::new()
+            .only_v6(true)
+            .bind("[::]:0".parse()?)
+            .await?;
+        let port = v6.local_addr()?.port();
+        let v4 = UdpSocket::bind(std::net::SocketAddr::from(([0, 0, 0, 0], port))).await?;
+        assert_eq!(port, v4.local_addr()?.port());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connected() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let ring = BufRing::builder(4).buf_cnt(4).buf_len(1024).build()?;
+        let s1 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        s1.connect(s2.local_addr()?).await?;
+        s2.connect(s1.local_addr()?).await?;
+        assert_eq!(s2.local_addr()?, s1.peer_addr()?);
+
+        let (res, _) = s1.send(Bytes::from_static(b"hello")).await;
+        assert_eq!(5, res?);
+        let (res, buf) = s2.recv(BytesMut::with_capacity(16)).await;
+        assert_eq!(b"hello", &buf[..res?]);
+
+        let (res, _) = s2.send(Bytes::from_static(b"world")).await;
+        assert_eq!(5, res?);
+        let buf = s1.recv_ring(&ring).await?;
+        assert_eq!(b"world", &buf[..]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn multicast() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let group = std::net::Ipv4Addr::new(239, 255, 42, 99);
+        let loopback = std::net::Ipv4Addr::LOCALHOST;
+
+        let receiver = UdpSocketBuilder::new()
+            .reuse_address(true)
+            .bind("0.0.0.0:0".parse()?)
+            .await?;
+        receiver.join_multicast_v4(group, loopback)?;
+
+        let sender = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        sender.set_multicast_if_v4(loopback)?;
+        sender.set_multicast_loop_v4(true)?;
+        sender.set_multicast_ttl_v4(1)?;
+        assert!(sender.multicast_loop_v4()?);
+        assert_eq!(1, sender.multicast_ttl_v4()?);
+        let dest = std::net::SocketAddr::from((group, receiver.local_addr()?.port()));
+        let (res, _) = sender.send_to(Bytes::from_static(b"discover"), dest).await;
+        assert_eq!(8, res?);
+
+        let (res, buf) = receiver.recv_from(BytesMut::with_capacity(16)).await;
+        let (n, addr) = res?;
+        assert_eq!(sender.local_addr()?, addr);
+        assert_eq!(b"discover", &buf[..n]);
+
+        receiver.leave_multicast_v4(group, loopback)?;
+        // Leaving a group which has not been joined fails.
+        assert!(receiver.leave_multicast_v4(group, loopback).is_err());
+
+        assert!(!sender.broadcast()?);
+        sender.set_broadcast(true)?;
+        assert!(sender.broadcast()?);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn multicast_options_v6() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let socket = UdpSocket::bind("[::1]:0".parse()?).await?;
+
+        socket.set_multicast_loop_v6(false)?;
+        assert!(!socket.multicast_loop_v6()?);
+        socket.set_multicast_loop_v6(true)?;
+        assert!(socket.multicast_loop_v6()?);
+
+        socket.set_multicast_hops_v6(8)?;
+        assert_eq!(8, socket.multicast_hops_v6()?);
+
+        Ok(())
+    })
+}
+
 struct UdpEchoServer {
     socket: UdpSocket,
 }
@@ -151,3 +457,27 @@ fn close_socket() -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     })
 }
+
+#[test]
+fn std_conversions() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let std_socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let s1 = UdpSocket::from_std(std_socket)?;
+        let addr = s1.local_addr()?;
+
+        let s2 = UdpSocket::bind("127.0.0.1:0".parse()?).await?;
+        s2.send_to(Bytes::from_static(b"hello"), addr).await.0?;
+        let (res, buf) = s1.recv_from(BytesMut::with_capacity(16)).await;
+        assert_eq!(5, res?.0);
+        assert_eq!(b"hello", &buf[..]);
+
+        // The extracted socket is still open and non-blocking.
+        let std_socket = s1.into_std().unwrap();
+        assert_eq!(addr, std_socket.local_addr()?);
+        let err = std_socket.recv(&mut [0u8; 16]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::WouldBlock, err.kind());
+
+        s2.close().await?;
+        Ok(())
+    })
+}
@@ -0,0 +1,132 @@
+use std::io::{Read, Seek, Write};
+use std::os::fd::AsFd;
+use std::pin::pin;
+
+use futures_util::StreamExt;
+use norn_executor::spawn;
+use norn_uring::net::{UnixDatagram, UnixListener, UnixSocketAddr, UnixStream};
+
+mod util;
+
+#[test]
+fn stream_pathname() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let addr = UnixSocketAddr::from_pathname(dir.join("sock"))?;
+        let listener = UnixListener::bind(addr.clone(), 32).await?;
+        assert_eq!(
+            Some(&*dir.join("sock")),
+            listener.local_addr()?.as_pathname()
+        );
+
+        let handle = spawn(async move {
+            let conn = UnixStream::connect(addr).await?;
+            let (res, _) = conn.send(b"hello".to_vec()).await;
+            res?;
+            conn.close().await
+        });
+
+        let (conn, peer) = listener.accept().await?;
+        assert!(peer.is_unnamed());
+        let cred = conn.peer_cred()?;
+        assert_eq!(std::process::id() as i32, cred.pid());
+        assert_eq!(unsafe { libc::getuid() }, cred.uid());
+
+        let mut received = vec![];
+        while received.len() < 5 {
+            let (res, buf) = conn.recv(Vec::with_capacity(16)).await;
+            let n = res?;
+            assert_ne!(0, n);
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(b"hello", &received[..]);
+        handle.await??;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn stream_abstract() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let name = format!("norn-uring-test-{}", std::process::id());
+        let addr = UnixSocketAddr::from_abstract_name(&name)?;
+        let listener = UnixListener::bind(addr.clone(), 32).await?;
+        assert_eq!(
+            Some(name.as_bytes()),
+            listener.local_addr()?.as_abstract_name()
+        );
+
+        let handle = spawn(async move {
+            let conn = UnixStream::connect(addr).await?;
+            assert_eq!(Some(name.as_bytes()), conn.peer_addr()?.as_abstract_name());
+            conn.close().await
+        });
+
+        let mut incoming = pin!(listener.incoming());
+        let conn = incoming.next().await.unwrap()?;
+        conn.close().await?;
+        handle.await??;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn stream_pass_fds() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let dir = util::ThreadNameTestDir::new();
+        let mut file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.join("file"))?;
+        file.write_all(b"passed")?;
+        file.rewind()?;
+
+        let (a, b) = UnixStream::pair()?;
+        let (res, _) = a.send_with_fds(b"x".to_vec(), &[file.as_fd()]).await;
+        assert_eq!(1, res?);
+
+        let (res, buf) = b.recv_with_fds(Vec::with_capacity(8)).await;
+        let (n, mut fds) = res?;
+        assert_eq!(b"x", &buf[..n]);
+        assert_eq!(1, fds.len());
+
+        // The received descriptor shares the file offset with the original.
+        let mut received = std::fs::File::from(fds.pop().unwrap());
+        let mut contents = String::new();
+        received.read_to_string(&mut contents)?;
+        assert_eq!("passed", contents);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn datagram() -> Result<(), Box<dyn std::error::Error>> {
+    util::with_test_env(|| async {
+        let id = std::process::id();
+        let addr1 = UnixSocketAddr::from_abstract_name(format!("norn-uring-dgram-1-{id}"))?;
+        let addr2 = UnixSocketAddr::from_abstract_name(format!("norn-uring-dgram-2-{id}"))?;
+        let s1 = UnixDatagram::bind(addr1.clone()).await?;
+        let s2 = UnixDatagram::bind(addr2.clone()).await?;
+
+        let (res, _) = s1.send_to(b"hello".to_vec(), &addr2).await;
+        assert_eq!(5, res?);
+        let (res, buf) = s2.recv_from(Vec::with_capacity(16)).await;
+        let (n, from) = res?;
+        assert_eq!(b"hello", &buf[..n]);
+        assert_eq!(addr1.as_abstract_name(), from.as_abstract_name());
+
+        // Connected pairs use send and recv.
+        let (a, b) = UnixDatagram::pair()?;
+        let (res, _) = a.send(b"world".to_vec()).await;
+        assert_eq!(5, res?);
+        let (res, buf) = b.recv(Vec::with_capacity(16)).await;
+        assert_eq!(b"world", &buf[..res?]);
+
+        Ok(())
+    })
+}